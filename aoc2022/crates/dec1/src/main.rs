@@ -1,68 +1,266 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::env;
+use std::fmt::Display;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::str::FromStr;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 struct ElveSupply {
-    id: i16,
-    calories: i32,
+    id: u32,
+    calories: u64,
+    items: u32,
 }
 
 impl ElveSupply {
-    fn new(id: i16) -> Self {
+    fn new(id: u32) -> Self {
         Self {
-            id: id,
+            id,
             calories: 0,
+            items: 0,
         }
     }
 
-    fn register_food(&mut self, value: i32) {
-        self.calories += value
+    fn register_food(&mut self, value: u64) {
+        self.calories += value;
+        self.items += 1;
     }
 }
 
+#[derive(Debug)]
+struct ParseError {
+    line: usize,
+    content: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {} is not a calorie count: {}",
+            self.line, self.content
+        )
+    }
+}
+
+// Aggregates the inventory one line at a time. Only the `k` best elves are
+// kept in full, the others are reduced to their totals for the statistics.
 struct ElveRegister {
-    elves: Vec<ElveSupply>,
-    counter: i16,
+    k: usize,
+    // Min-heap on calories, ties going to the lowest id.
+    top: BinaryHeap<Reverse<(u64, Reverse<u32>, u32)>>,
+    totals: Vec<u64>,
+    items: Vec<u32>,
+    current: Option<ElveSupply>,
 }
 
 impl ElveRegister {
-    fn new() -> Self {
+    fn new(k: usize) -> Self {
         Self {
-            elves: Vec::new(),
-            counter: 0,
+            k,
+            top: BinaryHeap::new(),
+            totals: Vec::new(),
+            items: Vec::new(),
+            current: None,
+        }
+    }
+
+    fn register_food(&mut self, value: u64) {
+        let id = self.totals.len() as u32 + 1;
+        self.current
+            .get_or_insert_with(|| ElveSupply::new(id))
+            .register_food(value);
+    }
+
+    // Closes the current elf, if any; repeated blank lines do not create
+    // empty elves.
+    fn move_to_new(&mut self) {
+        let Some(elve) = self.current.take() else {
+            return;
+        };
+        self.totals.push(elve.calories);
+        self.items.push(elve.items);
+        self.top
+            .push(Reverse((elve.calories, Reverse(elve.id), elve.items)));
+        if self.top.len() > self.k {
+            self.top.pop();
         }
     }
 
-    fn move_to_new(&mut self) -> &mut ElveSupply {
-        self.counter += 1;
-        self.elves.push(ElveSupply::new(self.counter));
-        return self.elves.last_mut().unwrap();
+    fn read_line(&mut self, line: usize, content: &str) -> Result<(), ParseError> {
+        let content = content.trim();
+        if content.is_empty() {
+            self.move_to_new();
+            return Ok(());
+        }
+        let value = u64::from_str(content).map_err(|_| ParseError {
+            line,
+            content: content.to_string(),
+        })?;
+        self.register_food(value);
+        Ok(())
     }
 
-    fn elves_by_calories(&self) -> Vec<&ElveSupply> {
-        let mut new_vec: Vec<&ElveSupply> = self.elves.iter().collect();
-        new_vec.sort_by(|a, b| b.calories.cmp(&a.calories));
-        return new_vec;
+    fn summary(mut self) -> Summary {
+        self.move_to_new();
+        let mut top: Vec<ElveSupply> = self
+            .top
+            .into_iter()
+            .map(|Reverse((calories, Reverse(id), items))| ElveSupply {
+                id,
+                calories,
+                items,
+            })
+            .collect();
+        top.sort_by(|a, b| b.calories.cmp(&a.calories).then(a.id.cmp(&b.id)));
+        let count = self.totals.len();
+        let total: u64 = self.totals.iter().sum();
+        self.totals.sort_unstable();
+        let median = match count {
+            0 => 0.0,
+            n if n % 2 == 1 => self.totals[n / 2] as f64,
+            n => (self.totals[n / 2 - 1] + self.totals[n / 2]) as f64 / 2.0,
+        };
+        Summary {
+            top,
+            count,
+            total,
+            mean: if count == 0 {
+                0.0
+            } else {
+                total as f64 / count as f64
+            },
+            median,
+            items: self.items,
+        }
+    }
+}
+
+struct Summary {
+    top: Vec<ElveSupply>,
+    count: usize,
+    total: u64,
+    mean: f64,
+    median: f64,
+    // Number of items carried by each elf, in inventory order.
+    items: Vec<u32>,
+}
+
+impl Summary {
+    fn max(&self) -> u64 {
+        self.top.first().map_or(0, |e| e.calories)
+    }
+
+    fn top_sum(&self, n: usize) -> u64 {
+        self.top.iter().take(n).map(|e| e.calories).sum()
+    }
+
+    fn to_json(&self, k: usize) -> String {
+        let top: Vec<String> = self
+            .top
+            .iter()
+            .take(k)
+            .map(|e| {
+                format!(
+                    "{{\"id\":{},\"calories\":{},\"items\":{}}}",
+                    e.id, e.calories, e.items
+                )
+            })
+            .collect();
+        let items: Vec<String> = self.items.iter().map(|i| i.to_string()).collect();
+        format!(
+            "{{\"max\":{},\"top_three\":{},\"elves\":{},\"total\":{},\"mean\":{},\"median\":{},\"top\":[{}],\"items\":[{}]}}",
+            self.max(),
+            self.top_sum(3),
+            self.count,
+            self.total,
+            self.mean,
+            self.median,
+            top.join(","),
+            items.join(",")
+        )
     }
 }
 
 fn main() -> std::io::Result<()> {
+    let usage = "Usage: dec1 [--top <k>] [--json]";
+    let mut k = 3;
+    let mut json = false;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--top" => k = args.next().expect(usage).parse().expect(usage),
+            "--json" => json = true,
+            _ => panic!("{}", usage),
+        }
+    }
     let file = File::open("./src/input.txt")?;
     let lines = BufReader::new(file).lines();
-    let mut elves_register = ElveRegister::new();
-    let mut current_elve = elves_register.move_to_new();
-    for l in lines {
-        let line_content = l.unwrap();
-        if line_content == String::from("") {
-            current_elve = elves_register.move_to_new();
-        } else {
-            let value = i32::from_str(&line_content).unwrap();
-            current_elve.register_food(value);
+    let mut elves_register = ElveRegister::new(k.max(3));
+    for (i, l) in lines.enumerate() {
+        if let Err(e) = elves_register.read_line(i + 1, &l?) {
+            panic!("{}", e);
         }
     }
-    let elves_carryings_most = elves_register.elves_by_calories();
-    let top_three = &elves_carryings_most[0..3];
-    let sum_of_calories = top_three.iter().fold(0, |acc, elve| elve.calories + acc);
-    println!("Top three elves gather {} together", sum_of_calories);
+    let summary = elves_register.summary();
+    if json {
+        println!("{}", summary.to_json(k));
+        return Ok(());
+    }
+    println!("The elf carrying the most has {} calories", summary.max());
+    println!("Top three elves gather {} together", summary.top_sum(3));
+    println!(
+        "{} elves carry {} calories, {:.1} on average, median {:.1}",
+        summary.count, summary.total, summary.mean, summary.median
+    );
+    println!(
+        "Each elf carries between {} and {} items",
+        summary.items.iter().min().unwrap_or(&0),
+        summary.items.iter().max().unwrap_or(&0)
+    );
+    for elve in summary.top.iter().take(k) {
+        println!(
+            "Elf {} carries {} calories in {} items",
+            elve.id, elve.calories, elve.items
+        );
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::ElveRegister;
+
+    fn register(k: usize) -> ElveRegister {
+        let mut register = ElveRegister::new(k);
+        let content = fs::read_to_string("./src/test.txt").unwrap();
+        for (i, line) in content.lines().enumerate() {
+            register.read_line(i + 1, line).unwrap();
+        }
+        register
+    }
+
+    #[test]
+    fn test_example() {
+        let summary = register(3).summary();
+        assert_eq!(summary.max(), 24000);
+        assert_eq!(summary.top_sum(3), 45000);
+        let ids: Vec<u32> = summary.top.iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![4, 3, 5]);
+        assert_eq!(summary.count, 5);
+        assert_eq!(summary.mean, 11000.0);
+        assert_eq!(summary.median, 10000.0);
+        assert_eq!(summary.items, vec![3, 1, 2, 3, 1]);
+    }
+
+    #[test]
+    fn test_bounded_heap() {
+        let summary = register(1).summary();
+        assert_eq!(summary.top.len(), 1);
+        assert_eq!(summary.max(), 24000);
+        assert!(register(1).read_line(1, "12 apples").is_err());
+    }
+}
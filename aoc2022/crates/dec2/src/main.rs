@@ -1,88 +1,465 @@
-use std::cmp::Ordering;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::str::FromStr;
 
-#[derive(PartialEq, Eq, Clone, Copy)]
-enum Choice {
-    Rock,
-    Paper,
-    Scissors,
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Outcome {
+    Loss,
+    Draw,
+    Win,
 }
 
-impl PartialOrd for Choice {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match (self, other) {
-            (Self::Rock, Self::Rock) => Some(Ordering::Equal),
-            (Self::Rock, Self::Scissors) => Some(Ordering::Greater),
-            (Self::Rock, Self::Paper) => Some(Ordering::Less),
-            (Self::Paper, Self::Paper) => Some(Ordering::Equal),
-            (Self::Paper, Self::Rock) => Some(Ordering::Greater),
-            (Self::Paper, Self::Scissors) => Some(Ordering::Less),
-            (Self::Scissors, Self::Rock) => Some(Ordering::Less),
-            (Self::Scissors, Self::Paper) => Some(Ordering::Greater),
-            (Self::Scissors, Self::Scissors) => Some(Ordering::Equal),
-        }
-    }
+impl Outcome {
+    const ALL: [Outcome; 3] = [Outcome::Loss, Outcome::Draw, Outcome::Win];
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Column {
+    // The second column is the move to play.
+    Move,
+    // The second column is the outcome to aim for.
+    Outcome,
 }
 
 #[derive(Debug)]
 struct InvalidInputError {}
 
-impl Choice {
-    fn from_first_column(input: &str) -> Result<Self, InvalidInputError> {
-        match input {
-            "A" => Ok(Self::Rock),
-            "B" => Ok(Self::Paper),
-            "C" => Ok(Self::Scissors),
-            _ => Err(InvalidInputError {}),
+// Meaning given to each symbol of the second column.
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Mapping {
+    Moves(Vec<usize>),
+    Outcomes(Vec<Outcome>),
+}
+
+// Above this many mappings the guide is not deciphered.
+const MAX_MAPPINGS: usize = 1_000_000;
+
+// Every mapping of the guide's second column, best score first.
+struct Ranking {
+    symbols: Vec<char>,
+    scored: Vec<(Mapping, i64)>,
+    // Position of the mapping the elf meant, if the guide uses only symbols
+    // it defines.
+    intended: Option<usize>,
+}
+
+// A cyclic hand game: with the moves in order, a move beats the ones an odd
+// number of steps before it (modulo the number of moves). With an odd number
+// of moves every move beats exactly half of the others.
+struct Game {
+    moves: Vec<String>,
+    scores: Vec<i32>,
+    // Points for a loss, a draw and a win.
+    outcome_points: [i32; 3],
+}
+
+impl Game {
+    fn new(
+        moves: Vec<String>,
+        scores: Vec<i32>,
+        outcome_points: [i32; 3],
+    ) -> Result<Self, InvalidInputError> {
+        // A single move could never win nor lose.
+        if moves.len() < 3
+            || moves.len().is_multiple_of(2)
+            || moves.len() > 26
+            || scores.len() != moves.len()
+        {
+            return Err(InvalidInputError {});
         }
+        Ok(Self {
+            moves,
+            scores,
+            outcome_points,
+        })
     }
 
-    fn from_second_column(opponant_choice: &Self, input: &str) -> Result<Self, InvalidInputError> {
-        match (input, opponant_choice) {
-            ("X", Self::Paper) => Ok(Self::Rock),
-            ("X", Self::Rock) => Ok(Self::Scissors),
-            ("X", Self::Scissors) => Ok(Self::Paper),
-            ("Y", _) => Ok(*opponant_choice),
-            ("Z", Self::Paper) => Ok(Self::Scissors),
-            ("Z", Self::Rock) => Ok(Self::Paper),
-            ("Z", Self::Scissors) => Ok(Self::Rock),
-            (_, _) => Err(InvalidInputError {}),
+    // Moves score 1, 2, 3... in order, with the usual points per outcome.
+    fn cyclic(moves: &[&str]) -> Result<Self, InvalidInputError> {
+        Self::new(
+            moves.iter().map(|m| m.to_string()).collect(),
+            (1..=moves.len() as i32).collect(),
+            [0, 3, 6],
+        )
+    }
+
+    fn rock_paper_scissors() -> Self {
+        Self::cyclic(&["Rock", "Paper", "Scissors"]).unwrap()
+    }
+
+    fn rock_paper_scissors_lizard_spock() -> Self {
+        Self::cyclic(&["Rock", "Paper", "Scissors", "Spock", "Lizard"]).unwrap()
+    }
+
+    fn beats(&self, a: usize, b: usize) -> bool {
+        let n = self.moves.len();
+        (a + n - b) % n % 2 == 1
+    }
+
+    fn outcome(&self, self_choice: usize, opponant_choice: usize) -> Outcome {
+        if self_choice == opponant_choice {
+            Outcome::Draw
+        } else if self.beats(self_choice, opponant_choice) {
+            Outcome::Win
+        } else {
+            Outcome::Loss
         }
     }
 
-    const fn value(&self) -> i32 {
-        match self {
-            Self::Rock => 1,
-            Self::Paper => 2,
-            Self::Scissors => 3,
+    // Best scoring move leading to the wanted outcome.
+    fn move_for(&self, opponant_choice: usize, outcome: Outcome) -> usize {
+        (0..self.moves.len())
+            .filter(|m| self.outcome(*m, opponant_choice) == outcome)
+            .max_by_key(|m| self.scores[*m])
+            .unwrap()
+    }
+
+    fn score_round(&self, self_choice: usize, opponant_choice: usize) -> i32 {
+        let outcome = self.outcome(self_choice, opponant_choice);
+        self.outcome_points[outcome as usize] + self.scores[self_choice]
+    }
+
+    // Opponent moves are written A, B, C...; own moves end at Z, so that
+    // rock-paper-scissors uses X, Y, Z.
+    fn move_in_first_column(&self, input: char) -> Result<usize, InvalidInputError> {
+        let index = (input as usize).wrapping_sub('A' as usize);
+        if index < self.moves.len() {
+            Ok(index)
+        } else {
+            Err(InvalidInputError {})
         }
     }
+
+    fn move_in_second_column(&self, input: char) -> Result<usize, InvalidInputError> {
+        let first = 'Z' as usize + 1 - self.moves.len();
+        let index = (input as usize).wrapping_sub(first);
+        if index < self.moves.len() {
+            Ok(index)
+        } else {
+            Err(InvalidInputError {})
+        }
+    }
+
+    fn outcome_from_second_column(input: char) -> Result<Outcome, InvalidInputError> {
+        let index = (input as usize).wrapping_sub('X' as usize);
+        Outcome::ALL.get(index).copied().ok_or(InvalidInputError {})
+    }
+
+    fn play(&self, guide: &[(char, char)], column: Column) -> Result<i64, InvalidInputError> {
+        let mut total_score = 0;
+        for (first, second) in guide {
+            let opponant_choice = self.move_in_first_column(*first)?;
+            let self_choice = match column {
+                Column::Move => self.move_in_second_column(*second)?,
+                Column::Outcome => {
+                    self.move_for(opponant_choice, Self::outcome_from_second_column(*second)?)
+                }
+            };
+            total_score += self.score_round(self_choice, opponant_choice) as i64;
+        }
+        Ok(total_score)
+    }
 }
 
-fn score_round(self_choice: &Choice, opponant_choice: &Choice) -> i32 {
-    let outcome_score = match self_choice.partial_cmp(opponant_choice).unwrap() {
-        Ordering::Less => 0,
-        Ordering::Equal => 3,
-        Ordering::Greater => 6,
-    };
-    let choice_score = self_choice.value();
-    return outcome_score + choice_score;
+impl Game {
+    fn choose(&self, opponant_choice: usize, mapping: &Mapping, symbol: usize) -> usize {
+        match mapping {
+            Mapping::Moves(moves) => moves[symbol],
+            Mapping::Outcomes(outcomes) => self.move_for(opponant_choice, outcomes[symbol]),
+        }
+    }
+
+    fn intended_mapping(&self, symbols: &[char], column: Column) -> Option<Mapping> {
+        match column {
+            Column::Move => symbols
+                .iter()
+                .map(|s| self.move_in_second_column(*s).ok())
+                .collect::<Option<Vec<usize>>>()
+                .map(Mapping::Moves),
+            Column::Outcome => symbols
+                .iter()
+                .map(|s| Self::outcome_from_second_column(*s).ok())
+                .collect::<Option<Vec<Outcome>>>()
+                .map(Mapping::Outcomes),
+        }
+    }
+
+    // Scores the guide under every one-to-one assignment of its second column
+    // symbols to moves, or to outcomes.
+    fn decipher(
+        &self,
+        guide: &[(char, char)],
+        column: Column,
+    ) -> Result<Ranking, InvalidInputError> {
+        let mut tally: BTreeMap<(usize, char), i64> = BTreeMap::new();
+        for (first, second) in guide {
+            *tally
+                .entry((self.move_in_first_column(*first)?, *second))
+                .or_default() += 1;
+        }
+        let mut symbols: Vec<char> = tally.keys().map(|(_, s)| *s).collect();
+        symbols.sort();
+        symbols.dedup();
+        let tally: Vec<(usize, usize, i64)> = tally
+            .into_iter()
+            .map(|((opponant_choice, s), count)| {
+                (opponant_choice, symbols.binary_search(&s).unwrap(), count)
+            })
+            .collect();
+        let base = match column {
+            Column::Move => self.moves.len(),
+            Column::Outcome => Outcome::ALL.len(),
+        };
+        // Distinct symbols must mean distinct things, so there can't be more
+        // of them than moves or outcomes.
+        let count = base
+            .checked_sub(symbols.len())
+            .and_then(|rest| (rest + 1..=base).try_fold(1usize, |acc, n| acc.checked_mul(n)));
+        if count.is_none_or(|count| count > MAX_MAPPINGS) {
+            return Err(InvalidInputError {});
+        }
+        let mut assignments: Vec<Vec<usize>> = vec![Vec::new()];
+        for _ in &symbols {
+            assignments = assignments
+                .into_iter()
+                .flat_map(|partial| {
+                    (0..base)
+                        .filter(|d| !partial.contains(d))
+                        .map(|d| {
+                            let mut extended = partial.clone();
+                            extended.push(d);
+                            extended
+                        })
+                        .collect::<Vec<Vec<usize>>>()
+                })
+                .collect();
+        }
+        let mut scored: Vec<(Mapping, i64)> = assignments
+            .into_iter()
+            .map(|digits| {
+                let mapping = match column {
+                    Column::Move => Mapping::Moves(digits),
+                    Column::Outcome => {
+                        Mapping::Outcomes(digits.into_iter().map(|d| Outcome::ALL[d]).collect())
+                    }
+                };
+                let score = tally
+                    .iter()
+                    .map(|(opponant_choice, symbol, count)| {
+                        let self_choice = self.choose(*opponant_choice, &mapping, *symbol);
+                        count * self.score_round(self_choice, *opponant_choice) as i64
+                    })
+                    .sum();
+                (mapping, score)
+            })
+            .collect();
+        scored.sort_by_key(|(_, score)| Reverse(*score));
+        let intended = self
+            .intended_mapping(&symbols, column)
+            .and_then(|m| scored.iter().position(|(mapping, _)| *mapping == m));
+        Ok(Ranking {
+            symbols,
+            scored,
+            intended,
+        })
+    }
+
+    fn describe(&self, symbols: &[char], mapping: &Mapping) -> String {
+        symbols
+            .iter()
+            .enumerate()
+            .map(|(i, s)| match mapping {
+                Mapping::Moves(moves) => format!("{}={}", s, self.moves[moves[i]]),
+                Mapping::Outcomes(outcomes) => format!("{}={:?}", s, outcomes[i]),
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+impl FromStr for Game {
+    type Err = InvalidInputError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rps" => Ok(Self::rock_paper_scissors()),
+            "rpsls" => Ok(Self::rock_paper_scissors_lizard_spock()),
+            moves => Self::cyclic(&moves.split(',').collect::<Vec<&str>>()),
+        }
+    }
+}
+
+fn parse_guide(
+    lines: impl Iterator<Item = String>,
+) -> Result<Vec<(char, char)>, InvalidInputError> {
+    lines
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| {
+            let mut chars = l.trim().chars();
+            match (chars.next(), chars.next(), chars.next(), chars.next()) {
+                (Some(first), Some(' '), Some(second), None) => Ok((first, second)),
+                _ => Err(InvalidInputError {}),
+            }
+        })
+        .collect()
+}
+
+fn parse_numbers(list: &str) -> Option<Vec<i32>> {
+    list.split(',').map(|n| n.trim().parse().ok()).collect()
 }
 
 fn main() -> std::io::Result<()> {
+    let usage = "Usage: dec2 [--game rps|rpsls|<move>,<move>,...] [--scores <score>,...] \
+                 [--points <loss>,<draw>,<win>] [--decipher]";
+    let mut game = Game::rock_paper_scissors();
+    let mut scores = None;
+    let mut points = None;
+    let mut decipher = false;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--game" => game = args.next().expect(usage).parse().expect(usage),
+            "--scores" => scores = Some(parse_numbers(&args.next().expect(usage)).expect(usage)),
+            "--points" => {
+                let list = parse_numbers(&args.next().expect(usage)).expect(usage);
+                points = Some(<[i32; 3]>::try_from(list).expect(usage));
+            }
+            "--decipher" => decipher = true,
+            _ => panic!("{}", usage),
+        }
+    }
+    if scores.is_some() || points.is_some() {
+        game = Game::new(
+            game.moves,
+            scores.unwrap_or(game.scores),
+            points.unwrap_or(game.outcome_points),
+        )
+        .expect(usage);
+    }
     let file = File::open("./src/input.txt")?;
-    let lines = BufReader::new(file).lines();
-    let mut total_score = 0;
-    for line in lines {
-        let line_content = line.unwrap();
-        if line_content.len() == 3 {
-            let opponant_choice = Choice::from_first_column(&line_content[0..1]).unwrap();
-            let self_choice =
-                Choice::from_second_column(&opponant_choice, &line_content[2..3]).unwrap();
-            total_score += score_round(&self_choice, &opponant_choice);
+    let lines = BufReader::new(file).lines().map(|l| l.unwrap());
+    let guide = parse_guide(lines).unwrap();
+    match game.play(&guide, Column::Move) {
+        Ok(score) => println!("Total score reading the guide as moves: {}", score),
+        Err(_) => println!("The guide can't be read as moves for this game"),
+    }
+    match game.play(&guide, Column::Outcome) {
+        Ok(score) => println!("Total score if everything goes right: {}", score),
+        Err(_) => println!("The guide can't be read as outcomes for this game"),
+    }
+    if decipher {
+        for column in [Column::Move, Column::Outcome] {
+            let ranking = match game.decipher(&guide, column) {
+                Ok(ranking) => ranking,
+                Err(_) => {
+                    println!(
+                        "The guide can't be deciphered as {:?} for this game",
+                        column
+                    );
+                    continue;
+                }
+            };
+            println!(
+                "Reading the second column as {:?}, {} possible mappings:",
+                column,
+                ranking.scored.len()
+            );
+            let mut shown = vec![("best", 0), ("worst", ranking.scored.len() - 1)];
+            if let Some(intended) = ranking.intended {
+                shown.insert(1, ("intended", intended));
+            }
+            for (label, rank) in shown {
+                let (mapping, score) = &ranking.scored[rank];
+                println!(
+                    "  {:<8} #{:<5} {:>7}  {}",
+                    label,
+                    rank + 1,
+                    score,
+                    game.describe(&ranking.symbols, mapping)
+                );
+            }
         }
     }
-    println!("Total score if everything goes right: {}", total_score);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{parse_guide, Column, Game, Mapping, Outcome};
+
+    #[test]
+    fn test_example() {
+        let content = fs::read_to_string("./src/test.txt").unwrap();
+        let guide = parse_guide(content.lines().map(String::from)).unwrap();
+        let game = Game::rock_paper_scissors();
+        assert_eq!(game.play(&guide, Column::Move).unwrap(), 15);
+        assert_eq!(game.play(&guide, Column::Outcome).unwrap(), 12);
+    }
+
+    #[test]
+    fn test_decipher() {
+        let content = fs::read_to_string("./src/test.txt").unwrap();
+        let guide = parse_guide(content.lines().map(String::from)).unwrap();
+        let game = Game::rock_paper_scissors();
+        let ranking = game.decipher(&guide, Column::Move).unwrap();
+        assert_eq!(ranking.symbols, vec!['X', 'Y', 'Z']);
+        assert_eq!(ranking.scored.len(), 6);
+        assert_eq!(ranking.scored[0], (Mapping::Moves(vec![2, 1, 0]), 24));
+        assert_eq!(ranking.scored[ranking.intended.unwrap()].1, 15);
+        let ranking = game.decipher(&guide, Column::Outcome).unwrap();
+        assert_eq!(ranking.scored.len(), 6);
+        assert_eq!(ranking.scored[ranking.intended.unwrap()].1, 12);
+        assert_eq!(
+            ranking.scored[0],
+            (
+                Mapping::Outcomes(vec![Outcome::Win, Outcome::Loss, Outcome::Draw]),
+                18
+            )
+        );
+        let rpsls = Game::rock_paper_scissors_lizard_spock();
+        assert_eq!(
+            rpsls.decipher(&guide, Column::Move).unwrap().scored.len(),
+            60
+        );
+        let guide = parse_guide(["A W", "B X", "C Y", "A Z"].into_iter().map(String::from));
+        assert!(game.decipher(&guide.unwrap(), Column::Outcome).is_err());
+    }
+
+    #[test]
+    fn test_rules() {
+        let game = Game::rock_paper_scissors_lizard_spock();
+        for m in 0..5 {
+            assert_eq!((0..5).filter(|o| game.beats(m, *o)).count(), 2);
+            for outcome in Outcome::ALL {
+                assert_eq!(game.outcome(game.move_for(m, outcome), m), outcome);
+            }
+        }
+        // Spock vaporizes rock, lizard eats paper, rock crushes lizard.
+        assert!(game.beats(3, 0));
+        assert!(game.beats(4, 1));
+        assert!(game.beats(0, 4));
+        assert!(Game::cyclic(&["a", "b"]).is_err());
+        assert!(Game::cyclic(&["a"]).is_err());
+    }
+
+    #[test]
+    fn test_custom_game() {
+        let guide = parse_guide(["A V", "C Z"].into_iter().map(String::from)).unwrap();
+        let game = Game::rock_paper_scissors_lizard_spock();
+        assert_eq!(game.play(&guide, Column::Move).unwrap(), 4 + 5);
+        assert!(game.play(&guide, Column::Outcome).is_err());
+        let moves = vec![String::from("Rock"), String::from("Paper")];
+        assert!(Game::new(moves, vec![1, 2], [0, 3, 6]).is_err());
+        let game = Game::rock_paper_scissors();
+        let game = Game::new(game.moves, vec![10, 20, 30], [0, 1, 2]).unwrap();
+        let guide = parse_guide(["A Y", "B X", "C Z"].into_iter().map(String::from)).unwrap();
+        assert_eq!(game.play(&guide, Column::Move).unwrap(), 22 + 10 + 31);
+        assert_eq!(game.play(&guide, Column::Outcome).unwrap(), 11 + 10 + 12);
+        assert!(Game::new(game.moves, vec![1, 2], [0, 3, 6]).is_err());
+    }
+}
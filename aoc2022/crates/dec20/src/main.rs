@@ -1,66 +1,265 @@
+use std::env;
+use std::fmt::Display;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-#[derive(Clone, Copy)]
-struct Elem {
-    value: i64,
-    index: usize,
+// Implicit treap node: the key of a node is its position in the in-order
+// traversal, so moves are a split/merge away. Nodes are stored in their
+// original order so that the mixing order is just the node index.
+#[derive(Clone, Copy, Default)]
+struct Node {
+    left: Option<usize>,
+    right: Option<usize>,
+    parent: Option<usize>,
+    priority: u64,
+    size: usize,
+}
+
+struct Mixer {
+    values: Vec<i64>,
+    nodes: Vec<Node>,
+    root: Option<usize>,
 }
 
-fn mix(table: &Vec<Elem>, values: Vec<Elem>) -> Vec<Elem> {
-    let length = table.len();
-    let mut result = values.clone();
-    for original in table.iter() {
-        let current_index = result
+impl Mixer {
+    fn new(values: Vec<i64>) -> Self {
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let nodes = values
             .iter()
-            .position(|e| e.index == original.index)
-            .unwrap();
-        let mut new_index =
-            ((current_index as i64 + original.value).rem_euclid((length - 1) as i64)) as usize;
-        if new_index == 0 && original.value != 0 {
-            new_index = length - 1;
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                Node {
+                    priority: seed,
+                    size: 1,
+                    ..Default::default()
+                }
+            })
+            .collect();
+        let mut mixer = Self {
+            values,
+            nodes,
+            root: None,
+        };
+        for i in 0..mixer.values.len() {
+            mixer.root = mixer.merge(mixer.root, Some(i));
+        }
+        mixer.detach(mixer.root);
+        mixer
+    }
+
+    fn size(&self, node: Option<usize>) -> usize {
+        node.map_or(0, |n| self.nodes[n].size)
+    }
+
+    fn detach(&mut self, node: Option<usize>) {
+        if let Some(n) = node {
+            self.nodes[n].parent = None;
         }
-        let mut next_result = Vec::with_capacity(length);
-        if new_index > current_index {
-            next_result.extend_from_slice(&result[0..current_index]);
-            next_result.extend_from_slice(&result[current_index + 1..new_index + 1]);
-            next_result.push(*original);
-            if new_index < length - 1 {
-                next_result.extend_from_slice(&result[new_index + 1..length])
+    }
+
+    fn update(&mut self, n: usize) {
+        let Node { left, right, .. } = self.nodes[n];
+        self.nodes[n].size = 1 + self.size(left) + self.size(right);
+        for child in [left, right].into_iter().flatten() {
+            self.nodes[child].parent = Some(n);
+        }
+    }
+
+    fn merge(&mut self, left: Option<usize>, right: Option<usize>) -> Option<usize> {
+        match (left, right) {
+            (None, r) => r,
+            (l, None) => l,
+            (Some(l), Some(r)) => {
+                if self.nodes[l].priority > self.nodes[r].priority {
+                    self.nodes[l].right = self.merge(self.nodes[l].right, Some(r));
+                    self.update(l);
+                    Some(l)
+                } else {
+                    self.nodes[r].left = self.merge(Some(l), self.nodes[r].left);
+                    self.update(r);
+                    Some(r)
+                }
             }
+        }
+    }
+
+    // Splits the tree so that the first `count` elements end up on the left.
+    fn split(&mut self, node: Option<usize>, count: usize) -> (Option<usize>, Option<usize>) {
+        let Some(n) = node else {
+            return (None, None);
+        };
+        let left_size = self.size(self.nodes[n].left);
+        if left_size >= count {
+            let (a, b) = self.split(self.nodes[n].left, count);
+            self.nodes[n].left = b;
+            self.update(n);
+            self.detach(a);
+            (a, Some(n))
         } else {
-            next_result.extend_from_slice(&result[0..new_index]);
-            next_result.push(*original);
-            next_result.extend_from_slice(&result[new_index..current_index]);
-            if current_index < length - 1 {
-                next_result.extend_from_slice(&result[current_index + 1..length])
+            let (a, b) = self.split(self.nodes[n].right, count - left_size - 1);
+            self.nodes[n].right = a;
+            self.update(n);
+            self.detach(b);
+            (Some(n), b)
+        }
+    }
+
+    fn position(&self, n: usize) -> usize {
+        let mut position = self.size(self.nodes[n].left);
+        let mut current = n;
+        while let Some(parent) = self.nodes[current].parent {
+            if self.nodes[parent].right == Some(current) {
+                position += self.size(self.nodes[parent].left) + 1;
             }
+            current = parent;
         }
-        result = next_result;
+        position
     }
-    return result;
+
+    fn move_elem(&mut self, n: usize) {
+        let length = self.values.len();
+        let value = self.values[n];
+        let current_index = self.position(n);
+        let (before, rest) = self.split(self.root, current_index);
+        let (_, after) = self.split(rest, 1);
+        let remaining = self.merge(before, after);
+        self.detach(remaining);
+        // Reduced first so that large values can't overflow.
+        let offset = value.rem_euclid((length - 1) as i64) as usize;
+        let mut new_index = (current_index + offset) % (length - 1);
+        if new_index == 0 && value != 0 {
+            new_index = length - 1;
+        }
+        let (before, after) = self.split(remaining, new_index);
+        let before = self.merge(before, Some(n));
+        self.root = self.merge(before, after);
+        self.detach(self.root);
+    }
+
+    fn mix(&mut self) {
+        if self.values.len() < 2 {
+            return;
+        }
+        for n in 0..self.values.len() {
+            self.move_elem(n);
+        }
+    }
+
+    fn to_vec(&self) -> Vec<i64> {
+        let mut result = Vec::with_capacity(self.values.len());
+        let mut stack = Vec::new();
+        let mut current = self.root;
+        while current.is_some() || !stack.is_empty() {
+            while let Some(n) = current {
+                stack.push(n);
+                current = self.nodes[n].left;
+            }
+            let n = stack.pop().unwrap();
+            result.push(self.values[n]);
+            current = self.nodes[n].right;
+        }
+        result
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct OverflowError {
+    value: i64,
+    key: i64,
+}
+
+impl Display for OverflowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "applying the key {} to {} overflows an i64",
+            self.key, self.value
+        )
+    }
+}
+
+fn decrypt(values: &[i64], key: i64, rounds: usize) -> Result<(i64, i64, i64), OverflowError> {
+    let values = values
+        .iter()
+        .map(|v| v.checked_mul(key).ok_or(OverflowError { value: *v, key }))
+        .collect::<Result<Vec<i64>, OverflowError>>()?;
+    let mut mixer = Mixer::new(values);
+    for _ in 0..rounds {
+        mixer.mix();
+    }
+    let result = mixer.to_vec();
+    let length = result.len();
+    let message_start = result.iter().position(|v| *v == 0).unwrap();
+    let x = result[(message_start + 1000) % length];
+    let y = result[(message_start + 2000) % length];
+    let z = result[(message_start + 3000) % length];
+    Ok((x, y, z))
 }
 
 fn main() -> std::io::Result<()> {
+    let mut key: i64 = 811589153;
+    let mut rounds: usize = 10;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next();
+        match (arg.as_str(), value) {
+            ("--key", Some(v)) => key = v.parse().expect("Invalid decryption key"),
+            ("--rounds", Some(v)) => rounds = v.parse().expect("Invalid round count"),
+            _ => panic!("Usage: dec20 [--key <decryption key>] [--rounds <count>]"),
+        }
+    }
     let file = File::open("./src/input.txt")?;
     let lines = BufReader::new(file).lines();
-    let values: Vec<i64> = lines
-        .map(|l| l.unwrap().parse::<i64>().unwrap() * 811589153)
-        .collect();
-    let table: Vec<Elem> = values
-        .into_iter()
-        .enumerate()
-        .map(|(i, v)| Elem { value: v, index: i })
-        .collect();
-    let length = table.len();
-    let mut result: Vec<Elem> = table.clone();
-    for _ in 0..10 {
-        result = mix(&table, result);
-    }
-    let message_start = result.iter().position(|e| e.value == 0).unwrap();
-    let x = result[(message_start + 1000) % length].value;
-    let y = result[(message_start + 2000) % length].value;
-    let z = result[(message_start + 3000) % length].value;
-    println!("x={}, y={}, z={}, sum={}", x, y, z, x + y + z);
+    let values: Vec<i64> = lines.map(|l| l.unwrap().parse::<i64>().unwrap()).collect();
+    match decrypt(&values, key, rounds) {
+        Ok((x, y, z)) => println!(
+            "x={}, y={}, z={}, sum={}",
+            x,
+            y,
+            z,
+            x as i128 + y as i128 + z as i128
+        ),
+        Err(e) => println!("Invalid decryption key: {}", e),
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{decrypt, Mixer, OverflowError};
+
+    fn test_values() -> Vec<i64> {
+        fs::read_to_string("./src/test.txt")
+            .unwrap()
+            .lines()
+            .map(|l| l.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_single_mix() {
+        let mut mixer = Mixer::new(test_values());
+        mixer.mix();
+        assert_eq!(mixer.to_vec(), vec![1, 2, -3, 4, 0, 3, -2]);
+    }
+
+    #[test]
+    fn test_decrypt() {
+        assert_eq!(decrypt(&test_values(), 1, 1), Ok((4, -3, 2)));
+        let (x, y, z) = decrypt(&test_values(), 811589153, 10).unwrap();
+        assert_eq!(x + y + z, 1623178306);
+        assert_eq!(
+            decrypt(&test_values(), i64::MAX / 2, 1),
+            Err(OverflowError {
+                value: -3,
+                key: i64::MAX / 2
+            })
+        );
+        let (x, y, z) = decrypt(&test_values(), i64::MAX / 4, 1).unwrap();
+        assert_eq!([x, y, z], [4, -3, 2].map(|v| v * (i64::MAX / 4)));
+    }
+}
@@ -1,15 +1,132 @@
 use nom::branch::alt;
 use nom::bytes::complete::tag;
+use nom::character::complete::i64 as number;
 use nom::character::complete::line_ending;
 use nom::multi::separated_list1;
-use nom::number::complete::double as number;
 use nom::{
     character::complete::{alpha1, one_of, space1},
     IResult,
 };
 use std::collections::HashMap;
-use std::{fs::File, io::Read};
+use std::fmt::Display;
+use std::{env, fs, fs::File, io::Read};
 
+fn gcd(a: i128, b: i128) -> Option<i128> {
+    if b == 0 {
+        a.checked_abs()
+    } else {
+        gcd(b, a.checked_rem(b)?)
+    }
+}
+
+// Exact fraction. Every operation is checked and gives `None` when the
+// numerator or denominator would not fit.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Rational {
+    num: i128,
+    den: i128,
+}
+
+impl Rational {
+    fn new(num: i128, den: i128) -> Option<Self> {
+        let divisor = gcd(num, den)?.checked_mul(den.signum())?;
+        Some(Self {
+            num: num.checked_div(divisor)?,
+            den: den.checked_div(divisor)?,
+        })
+    }
+
+    fn is_zero(&self) -> bool {
+        self.num == 0
+    }
+
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        Self::new(
+            self.num
+                .checked_mul(rhs.den)?
+                .checked_add(rhs.num.checked_mul(self.den)?)?,
+            self.den.checked_mul(rhs.den)?,
+        )
+    }
+
+    fn checked_sub(self, rhs: Self) -> Option<Self> {
+        Self::new(
+            self.num
+                .checked_mul(rhs.den)?
+                .checked_sub(rhs.num.checked_mul(self.den)?)?,
+            self.den.checked_mul(rhs.den)?,
+        )
+    }
+
+    fn checked_mul(self, rhs: Self) -> Option<Self> {
+        Self::new(
+            self.num.checked_mul(rhs.num)?,
+            self.den.checked_mul(rhs.den)?,
+        )
+    }
+
+    fn checked_div(self, rhs: Self) -> Option<Self> {
+        Self::new(
+            self.num.checked_mul(rhs.den)?,
+            self.den.checked_mul(rhs.num)?,
+        )
+    }
+}
+
+impl From<i128> for Rational {
+    fn from(value: i128) -> Self {
+        Self { num: value, den: 1 }
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum SolveError {
+    UnknownMonkey(String),
+    Cycle(String),
+    DivisionByZero(String),
+    NotAnOperation(String),
+    UnknownNotFound(String),
+    UnknownOnBothSides(String),
+    NonLinear(String),
+    NoUniqueSolution(String),
+    Overflow(String),
+}
+
+impl Display for SolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownMonkey(id) => write!(f, "monkey {} is referenced but never defined", id),
+            Self::Cycle(id) => write!(f, "monkey {} depends on its own result", id),
+            Self::DivisionByZero(id) => write!(f, "monkey {} divides by zero", id),
+            Self::NotAnOperation(id) => write!(f, "monkey {} does not compare two monkeys", id),
+            Self::UnknownNotFound(id) => write!(f, "{} does not influence the equality", id),
+            Self::UnknownOnBothSides(id) => {
+                write!(f, "the unknown appears on both sides of monkey {}", id)
+            }
+            Self::NonLinear(id) => write!(
+                f,
+                "monkey {} combines two results depending on the unknown",
+                id
+            ),
+            Self::NoUniqueSolution(id) => {
+                write!(f, "monkey {} can not be inverted to a single value", id)
+            }
+            Self::Overflow(id) => write!(f, "monkey {} yells a number too large to hold", id),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
 enum Operation {
     Add,
     Multiply,
@@ -18,14 +135,81 @@ enum Operation {
 }
 
 impl Operation {
-    fn apply(&self, lhs: &f64, rhs: &f64) -> f64 {
+    fn apply(&self, lhs: Rational, rhs: Rational, name: &str) -> Result<Rational, SolveError> {
+        let result = match self {
+            Self::Add => lhs.checked_add(rhs),
+            Self::Divide if rhs.is_zero() => {
+                return Err(SolveError::DivisionByZero(name.to_string()))
+            }
+            Self::Divide => lhs.checked_div(rhs),
+            Self::Multiply => lhs.checked_mul(rhs),
+            Self::Substract => lhs.checked_sub(rhs),
+        };
+        result.ok_or_else(|| SolveError::Overflow(name.to_string()))
+    }
+
+    fn precedence(&self) -> u8 {
         match self {
-            Self::Add => lhs + rhs,
-            Self::Divide => lhs / rhs,
-            Self::Multiply => lhs * rhs,
-            Self::Substract => lhs - rhs,
+            Self::Add | Self::Substract => 1,
+            Self::Multiply | Self::Divide => 2,
         }
     }
+
+    fn is_associative(&self) -> bool {
+        matches!(self, Self::Add | Self::Multiply)
+    }
+
+    // Finds x such that `x op rhs == target`.
+    fn solve_lhs(
+        &self,
+        rhs: Rational,
+        target: Rational,
+        name: &str,
+    ) -> Result<Rational, SolveError> {
+        let result = match self {
+            Self::Add => target.checked_sub(rhs),
+            Self::Substract => target.checked_add(rhs),
+            Self::Multiply if rhs.is_zero() => {
+                return Err(SolveError::NoUniqueSolution(name.to_string()))
+            }
+            Self::Multiply => target.checked_div(rhs),
+            Self::Divide => target.checked_mul(rhs),
+        };
+        result.ok_or_else(|| SolveError::Overflow(name.to_string()))
+    }
+
+    // Finds x such that `lhs op x == target`.
+    fn solve_rhs(
+        &self,
+        lhs: Rational,
+        target: Rational,
+        name: &str,
+    ) -> Result<Rational, SolveError> {
+        let result = match self {
+            Self::Add => target.checked_sub(lhs),
+            Self::Substract => lhs.checked_sub(target),
+            Self::Multiply if lhs.is_zero() => {
+                return Err(SolveError::NoUniqueSolution(name.to_string()))
+            }
+            Self::Multiply => target.checked_div(lhs),
+            Self::Divide if target.is_zero() => {
+                return Err(SolveError::NoUniqueSolution(name.to_string()))
+            }
+            Self::Divide => lhs.checked_div(target),
+        };
+        result.ok_or_else(|| SolveError::Overflow(name.to_string()))
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Add => "+",
+            Self::Multiply => "*",
+            Self::Substract => "-",
+            Self::Divide => "/",
+        })
+    }
 }
 
 struct OperationMonkey<'a> {
@@ -36,7 +220,7 @@ struct OperationMonkey<'a> {
 
 enum MonkeyType<'a> {
     Operation(OperationMonkey<'a>),
-    Value(f64),
+    Value(i64),
 }
 
 struct Monkey<'a> {
@@ -44,52 +228,284 @@ struct Monkey<'a> {
     kind: MonkeyType<'a>,
 }
 
+enum Node {
+    Value(Rational),
+    Operation {
+        lhs: usize,
+        rhs: usize,
+        operation: Operation,
+    },
+}
+
 struct Troop<'a> {
-    values: HashMap<&'a str, f64>,
-    monkeys: Vec<Monkey<'a>>,
+    ids: HashMap<&'a str, usize>,
+    names: Vec<&'a str>,
+    nodes: Vec<Node>,
+    // Every monkey appears after the monkeys it listens to.
+    order: Vec<usize>,
 }
 
 impl<'a> Troop<'a> {
-    fn new(monkeys: Vec<Monkey<'a>>) -> Self {
-        Self {
-            monkeys,
-            values: HashMap::new(),
+    fn new(monkeys: Vec<Monkey<'a>>) -> Result<Self, SolveError> {
+        let ids: HashMap<&str, usize> =
+            monkeys.iter().enumerate().map(|(i, m)| (m.id, i)).collect();
+        let resolve = |id: &str| {
+            ids.get(id)
+                .copied()
+                .ok_or_else(|| SolveError::UnknownMonkey(id.to_string()))
+        };
+        let nodes = monkeys
+            .iter()
+            .map(|m| match &m.kind {
+                MonkeyType::Value(v) => Ok(Node::Value(Rational::from(*v as i128))),
+                MonkeyType::Operation(op) => Ok(Node::Operation {
+                    lhs: resolve(op.lhs)?,
+                    rhs: resolve(op.rhs)?,
+                    operation: op.operation,
+                }),
+            })
+            .collect::<Result<Vec<Node>, SolveError>>()?;
+        let names = monkeys.iter().map(|m| m.id).collect();
+        let mut troop = Self {
+            ids,
+            names,
+            nodes,
+            order: Vec::new(),
+        };
+        troop.order = troop.topological_order()?;
+        Ok(troop)
+    }
+
+    fn topological_order(&self) -> Result<Vec<usize>, SolveError> {
+        let mut waiting_for = vec![0; self.nodes.len()];
+        let mut listeners = vec![Vec::new(); self.nodes.len()];
+        for (i, node) in self.nodes.iter().enumerate() {
+            if let Node::Operation { lhs, rhs, .. } = node {
+                waiting_for[i] = 2;
+                listeners[*lhs].push(i);
+                listeners[*rhs].push(i);
+            }
+        }
+        let mut order: Vec<usize> = (0..self.nodes.len())
+            .filter(|i| waiting_for[*i] == 0)
+            .collect();
+        let mut next = 0;
+        while next < order.len() {
+            for listener in listeners[order[next]].iter() {
+                waiting_for[*listener] -= 1;
+                if waiting_for[*listener] == 0 {
+                    order.push(*listener);
+                }
+            }
+            next += 1;
+        }
+        match waiting_for.iter().position(|w| *w > 0) {
+            Some(stuck) => Err(SolveError::Cycle(self.names[stuck].to_string())),
+            None => Ok(order),
+        }
+    }
+
+    fn id(&self, name: &str) -> Result<usize, SolveError> {
+        self.ids
+            .get(name)
+            .copied()
+            .ok_or_else(|| SolveError::UnknownMonkey(name.to_string()))
+    }
+
+    // Values of every monkey, `None` for those depending on the unknown.
+    fn evaluate(&self, unknown: Option<usize>) -> Result<Vec<Option<Rational>>, SolveError> {
+        let mut values = vec![None; self.nodes.len()];
+        for &i in self.order.iter() {
+            if Some(i) == unknown {
+                continue;
+            }
+            values[i] = match &self.nodes[i] {
+                Node::Value(v) => Some(*v),
+                Node::Operation {
+                    lhs,
+                    rhs,
+                    operation,
+                } => match (values[*lhs], values[*rhs]) {
+                    (Some(l), Some(r)) => Some(operation.apply(l, r, self.names[i])?),
+                    _ => None,
+                },
+            };
         }
+        Ok(values)
     }
 
-    fn move_up(&mut self) {
-        self.monkeys.iter().for_each(|monkey| {
-            if self.values.contains_key(monkey.id) {
-                return;
+    fn compute(&self, root: &str) -> Result<Rational, SolveError> {
+        let root = self.id(root)?;
+        Ok(self.evaluate(None)?[root].unwrap())
+    }
+
+    // Value each monkey between `root` and `unknown` must yell for both
+    // operands of `root` to be equal, ending with `unknown` itself.
+    fn solution_path(
+        &self,
+        root: &str,
+        unknown: &str,
+    ) -> Result<Vec<(usize, Rational)>, SolveError> {
+        let root = self.id(root)?;
+        let unknown = self.id(unknown)?;
+        let values = self.evaluate(Some(unknown))?;
+        let (lhs, rhs) = match self.nodes[root] {
+            Node::Operation { lhs, rhs, .. } => (lhs, rhs),
+            Node::Value(_) => return Err(SolveError::NotAnOperation(self.names[root].to_string())),
+        };
+        let (mut current, mut target) = match (values[lhs], values[rhs]) {
+            (None, Some(target)) => (lhs, target),
+            (Some(target), None) => (rhs, target),
+            (None, None) => {
+                return Err(SolveError::UnknownOnBothSides(self.names[root].to_string()))
+            }
+            (Some(_), Some(_)) => {
+                return Err(SolveError::UnknownNotFound(self.names[unknown].to_string()))
             }
-            match &monkey.kind {
-                MonkeyType::Operation(op) => {
-                    match (self.values.get(op.lhs), self.values.get(op.rhs)) {
-                        (Some(lhs), Some(rhs)) => {
-                            let value = op.operation.apply(lhs, rhs);
-                            self.values.insert(monkey.id, value);
+        };
+        let mut path = vec![(current, target)];
+        while current != unknown {
+            let name = self.names[current];
+            let (lhs, rhs, operation) = match self.nodes[current] {
+                Node::Operation {
+                    lhs,
+                    rhs,
+                    operation,
+                } => (lhs, rhs, operation),
+                Node::Value(_) => unreachable!("Constant monkeys never depend on the unknown"),
+            };
+            let (next, solved) = match (values[lhs], values[rhs]) {
+                (None, Some(r)) => (lhs, operation.solve_lhs(r, target, name)),
+                (Some(l), None) => (rhs, operation.solve_rhs(l, target, name)),
+                _ => return Err(SolveError::NonLinear(name.to_string())),
+            };
+            target = solved?;
+            current = next;
+            path.push((current, target));
+        }
+        Ok(path)
+    }
+
+    // Finds the value of `unknown` for which both operands of `root` are equal.
+    fn solve(&self, root: &str, unknown: &str) -> Result<Rational, SolveError> {
+        Ok(self.solution_path(root, unknown)?.last().unwrap().1)
+    }
+
+    fn render(&self, node: usize, values: &[Option<Rational>], unknown: Option<usize>) -> String {
+        if Some(node) == unknown {
+            return self.names[node].to_string();
+        }
+        if let Some(value) = values[node] {
+            return value.to_string();
+        }
+        match self.nodes[node] {
+            Node::Value(v) => v.to_string(),
+            Node::Operation {
+                lhs,
+                rhs,
+                operation,
+            } => {
+                let operand = |child: usize, is_rhs: bool| {
+                    let rendered = self.render(child, values, unknown);
+                    let needs_parens = match (&self.nodes[child], values[child]) {
+                        (_, Some(value)) => value.num < 0 || value.den != 1,
+                        (
+                            Node::Operation {
+                                operation: inner, ..
+                            },
+                            None,
+                        ) if Some(child) != unknown => {
+                            inner.precedence() < operation.precedence()
+                                || (is_rhs
+                                    && inner.precedence() == operation.precedence()
+                                    && !operation.is_associative())
                         }
-                        _ => {}
+                        _ => false,
+                    };
+                    if needs_parens {
+                        format!("({})", rendered)
+                    } else {
+                        rendered
                     }
-                }
-                _ => {}
+                };
+                format!(
+                    "{} {} {}",
+                    operand(lhs, false),
+                    operation,
+                    operand(rhs, true)
+                )
             }
-        })
+        }
+    }
+
+    // Infix formula of the monkey `root`, with every subtree that does not
+    // depend on `unknown` folded into its value.
+    fn formula(&self, root: &str, unknown: Option<&str>) -> Result<String, SolveError> {
+        let root = self.id(root)?;
+        let unknown = unknown.map(|u| self.id(u)).transpose()?;
+        let values = self.evaluate(unknown)?;
+        Ok(self.render(root, &values, unknown))
+    }
+
+    // Both operands of `root` as formulas of `unknown`, as an equality.
+    fn equation(&self, root: &str, unknown: &str) -> Result<String, SolveError> {
+        let root_id = self.id(root)?;
+        let unknown = self.id(unknown)?;
+        let values = self.evaluate(Some(unknown))?;
+        match self.nodes[root_id] {
+            Node::Operation { lhs, rhs, .. } => Ok(format!(
+                "{} = {}",
+                self.render(lhs, &values, Some(unknown)),
+                self.render(rhs, &values, Some(unknown))
+            )),
+            Node::Value(_) => Err(SolveError::NotAnOperation(root.to_string())),
+        }
     }
 
-    fn compute(&mut self, initial_value: f64) -> f64 {
-        self.values = HashMap::from_iter(self.monkeys.iter().filter_map(|m| match m.kind {
-            MonkeyType::Value(v) => Some((m.id, v)),
-            _ => None,
-        }));
-        self.values.insert("humn", initial_value);
-        loop {
-            self.move_up();
-            match self.values.get("root") {
-                Some(result) => return *result,
-                _ => {}
+    // Graphviz description of the whole troop. Monkeys depending on `unknown`
+    // are highlighted, and those on the solved path carry the value they need
+    // to yell.
+    fn to_dot(&self, root: &str, unknown: &str) -> Result<String, SolveError> {
+        let unknown_id = self.id(unknown)?;
+        let values = self.evaluate(Some(unknown_id))?;
+        let targets: HashMap<usize, Rational> = match self.solution_path(root, unknown) {
+            Ok(path) => path.into_iter().collect(),
+            Err(_) => HashMap::new(),
+        };
+        let mut dot = String::from("digraph troop {\n    rankdir=BT;\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            let mut label = match node {
+                Node::Value(v) => format!("{}\\n{}", self.names[i], v),
+                Node::Operation { operation, .. } => format!("{}\\n{}", self.names[i], operation),
+            };
+            if let Some(target) = targets.get(&i) {
+                label.push_str(&format!("\\nmust be {}", target));
             }
+            let style = match values[i] {
+                None => ", color=red, fontcolor=red",
+                Some(_) => "",
+            };
+            dot.push_str(&format!(
+                "    \"{}\" [label=\"{}\"{}];\n",
+                self.names[i], label, style
+            ));
         }
+        for (i, node) in self.nodes.iter().enumerate() {
+            if let Node::Operation { lhs, rhs, .. } = node {
+                for child in [lhs, rhs] {
+                    let style = match values[*child] {
+                        None => " [color=red]",
+                        Some(_) => "",
+                    };
+                    dot.push_str(&format!(
+                        "    \"{}\" -> \"{}\"{};\n",
+                        self.names[*child], self.names[i], style
+                    ));
+                }
+            }
+        }
+        dot.push_str("}\n");
+        Ok(dot)
     }
 }
 
@@ -107,7 +523,7 @@ fn operation(input: &str) -> IResult<&str, Operation> {
     ))
 }
 
-fn operation_monkey(input: &str) -> IResult<&str, MonkeyType> {
+fn operation_monkey(input: &str) -> IResult<&str, MonkeyType<'_>> {
     let (input, lhs) = alpha1(input)?;
     let (input, _) = space1(input)?;
     let (input, operand) = operation(input)?;
@@ -123,54 +539,124 @@ fn operation_monkey(input: &str) -> IResult<&str, MonkeyType> {
     ))
 }
 
-fn value_monkey(input: &str) -> IResult<&str, MonkeyType> {
+fn value_monkey(input: &str) -> IResult<&str, MonkeyType<'_>> {
     let (input, value) = number(input)?;
     Ok((input, MonkeyType::Value(value)))
 }
 
-fn monkey(input: &str) -> IResult<&str, Monkey> {
+fn monkey(input: &str) -> IResult<&str, Monkey<'_>> {
     let (input, id) = alpha1(input)?;
     let (input, _) = tag(": ")(input)?;
     let (input, kind) = alt((value_monkey, operation_monkey))(input)?;
-    if id == "root" {
-        match kind {
-            MonkeyType::Operation(op) => {
-                return Ok((
-                    input,
-                    Monkey {
-                        id,
-                        kind: MonkeyType::Operation(OperationMonkey {
-                            lhs: op.lhs,
-                            rhs: op.rhs,
-                            operation: Operation::Substract,
-                        }),
-                    },
-                ));
-            }
-            _ => {}
-        }
-    }
     Ok((input, Monkey { id, kind }))
 }
 
-fn troop(input: &str) -> IResult<&str, Troop> {
-    let (input, monkeys) = separated_list1(line_ending, monkey)(input)?;
-    Ok((input, Troop::new(monkeys)))
+fn troop(input: &str) -> IResult<&str, Vec<Monkey<'_>>> {
+    separated_list1(line_ending, monkey)(input)
 }
 
 fn main() {
+    let mut formulas = Vec::new();
+    let mut show_equation = false;
+    let mut dot_path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let usage = "Usage: dec21 [--formula <monkey>]... [--dot <file>] [--equation]";
+        match arg.as_str() {
+            "--equation" => show_equation = true,
+            "--formula" => formulas.push(args.next().expect(usage)),
+            "--dot" => dot_path = Some(args.next().expect(usage)),
+            _ => panic!("{}", usage),
+        }
+    }
     let mut file = File::open("./src/input.txt").unwrap();
     let mut content = String::new();
     file.read_to_string(&mut content).unwrap();
-    let (_, mut troop) = troop(&content).unwrap();
-    println!("Parsed {} monkeys", troop.monkeys.len());
-    let own_value = 0.;
-    let first_res = troop.compute(own_value);
-    let second_res = troop.compute(own_value + 100000000000.);
-    println!("First got {} then {}", first_res, second_res);
-    let a = second_res - first_res;
-    let b = first_res;
-    println!("a={} and b={}", a, b);
-    let res = -b * 100000000000. / a;
-    println!("We should yell {}", res);
+    let (_, monkeys) = troop(&content).unwrap();
+    println!("Parsed {} monkeys", monkeys.len());
+    let troop = match Troop::new(monkeys) {
+        Ok(troop) => troop,
+        Err(e) => return println!("Invalid troop: {}", e),
+    };
+    match troop.compute("root") {
+        Ok(value) => println!("The root monkey yells {}", value),
+        Err(e) => println!("Could not compute root: {}", e),
+    }
+    match troop.solve("root", "humn") {
+        Ok(value) => println!("We should yell {}", value),
+        Err(e) => println!("Could not solve for humn: {}", e),
+    }
+    if show_equation {
+        match troop.equation("root", "humn") {
+            Ok(equation) => println!("{}", equation),
+            Err(e) => println!("Could not write the equation: {}", e),
+        }
+    }
+    for monkey in formulas {
+        match troop.formula(&monkey, Some("humn")) {
+            Ok(formula) => println!("{}: {}", monkey, formula),
+            Err(e) => println!("Could not write the formula of {}: {}", monkey, e),
+        }
+    }
+    if let Some(path) = dot_path {
+        match troop.to_dot("root", "humn") {
+            Ok(dot) => fs::write(&path, dot).unwrap(),
+            Err(e) => println!("Could not export the troop: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{troop, Rational, SolveError, Troop};
+
+    #[test]
+    fn test_example() {
+        let content = fs::read_to_string("./src/test.txt").unwrap();
+        let (_, monkeys) = troop(&content).unwrap();
+        let troop = Troop::new(monkeys).unwrap();
+        assert_eq!(troop.compute("root"), Ok(Rational::from(152)));
+        assert_eq!(troop.solve("root", "humn"), Ok(Rational::from(301)));
+        assert_eq!(
+            troop.equation("root", "humn"),
+            Ok("(4 + 2 * (humn - 3)) / 4 = 150".to_string())
+        );
+        assert_eq!(troop.formula("root", None), Ok("152".to_string()));
+        let dot = troop.to_dot("root", "humn").unwrap();
+        assert!(dot.contains(r#""humn" [label="humn\n5\nmust be 301", color=red, fontcolor=red];"#));
+        assert!(dot.contains(r#""pppw" [label="pppw\n/\nmust be 150", color=red"#));
+        assert!(dot.contains(r#""sjmn" [label="sjmn\n*"];"#));
+    }
+
+    #[test]
+    fn test_non_linear() {
+        let content = "root: abcd + efgh\nabcd: humn * humn\nefgh: 4\nhumn: 1";
+        let (_, monkeys) = troop(content).unwrap();
+        let troop = Troop::new(monkeys).unwrap();
+        assert_eq!(
+            troop.solve("root", "humn"),
+            Err(SolveError::NonLinear("abcd".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_overflow() {
+        let content = "root: abcd + efgh\nabcd: humn * big\nbig: 9223372036854775807\nefgh: sqr * big\nsqr: big * big\nhumn: 3";
+        let (_, monkeys) = troop(content).unwrap();
+        let troop = Troop::new(monkeys).unwrap();
+        assert_eq!(
+            troop.compute("root"),
+            Err(SolveError::Overflow("efgh".to_string()))
+        );
+        assert_eq!(
+            troop.solve("root", "humn"),
+            Err(SolveError::Overflow("efgh".to_string()))
+        );
+        assert_eq!(
+            Rational::from(i128::MAX).checked_add(Rational::from(1)),
+            None
+        );
+    }
 }
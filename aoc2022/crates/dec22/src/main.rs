@@ -4,12 +4,14 @@ use nom::combinator::map;
 use nom::multi::{many1, separated_list1};
 use nom::sequence::separated_pair;
 use nom::IResult;
-use std::collections::HashMap;
-use std::fs::File;
+use std::env;
+use std::fmt::Display;
+use std::fs::{self, File};
 use std::io::Read;
-use std::ops::{Add, Range};
+use std::ops::{Add, Neg, Range};
+use std::str::FromStr;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 struct Pos {
     x: usize,
     y: usize,
@@ -26,7 +28,7 @@ impl Add<&(i32, i32)> for &Pos {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Direction {
     Left,
     Right,
@@ -45,7 +47,7 @@ impl Direction {
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 enum Cell {
     Void,
     Open,
@@ -63,136 +65,162 @@ impl Bounds {
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Vec3 {
+    x: i32,
+    y: i32,
+    z: i32,
+}
+
+impl Neg for Vec3 {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum InvalidNet {
+    Area(usize),
+    FaceCount(usize),
+    Disconnected,
+    OverlappingFaces,
+}
+
+impl Display for InvalidNet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Area(area) => write!(f, "{} tiles can not be split in 6 square faces", area),
+            Self::FaceCount(count) => write!(f, "found {} connected faces instead of 6", count),
+            Self::Disconnected => write!(f, "the 6 faces are not connected by their edges"),
+            Self::OverlappingFaces => write!(f, "several faces fold onto the same side"),
+        }
+    }
+}
+
+// A face of the folded cube, along with where its normal and the map's
+// right and down directions point to once folded.
 struct Face {
     bounds: Bounds,
+    normal: Vec3,
+    right: Vec3,
+    down: Vec3,
 }
 
 impl Face {
-    fn at(&self, position: usize, from_dir: &Direction, direction: &Direction) -> Pos {
-        let y = match (from_dir, direction) {
-            (_, Direction::Bottom) => self.bounds.y.start,
-            (_, Direction::Top) => self.bounds.y.end - 1,
-            (Direction::Left, Direction::Left)
-            | (Direction::Right, Direction::Right)
-            | (Direction::Top, Direction::Right)
-            | (Direction::Bottom, Direction::Left) => self.bounds.y.start + position,
-            (Direction::Bottom, Direction::Right)
-            | (Direction::Top, Direction::Left)
-            | (Direction::Right, Direction::Left)
-            | (Direction::Left, Direction::Right) => self.bounds.y.end - 1 - position,
-        };
-        let x = match (from_dir, direction) {
-            (_, Direction::Right) => self.bounds.x.start,
-            (_, Direction::Left) => self.bounds.x.end - 1,
-            (Direction::Top, Direction::Top)
-            | (Direction::Bottom, Direction::Bottom)
-            | (Direction::Left, Direction::Bottom)
-            | (Direction::Right, Direction::Top) => self.bounds.x.start + position,
-            (Direction::Bottom, Direction::Top)
-            | (Direction::Top, Direction::Bottom)
-            | (Direction::Right, Direction::Bottom)
-            | (Direction::Left, Direction::Top) => self.bounds.x.end - 1 - position,
-        };
-        Pos { x, y }
+    fn direction(&self, direction: &Direction) -> Vec3 {
+        match direction {
+            Direction::Right => self.right,
+            Direction::Left => -self.right,
+            Direction::Bottom => self.down,
+            Direction::Top => -self.down,
+        }
+    }
+
+    fn entry(&self, position: usize, direction: &Direction) -> Pos {
+        match direction {
+            Direction::Right => Pos {
+                x: self.bounds.x.start,
+                y: self.bounds.y.start + position,
+            },
+            Direction::Left => Pos {
+                x: self.bounds.x.end - 1,
+                y: self.bounds.y.start + position,
+            },
+            Direction::Bottom => Pos {
+                x: self.bounds.x.start + position,
+                y: self.bounds.y.start,
+            },
+            Direction::Top => Pos {
+                x: self.bounds.x.start + position,
+                y: self.bounds.y.end - 1,
+            },
+        }
     }
 }
 
 struct Cube {
     faces: Vec<Face>,
+    size: usize,
 }
 
 impl Cube {
-    fn new_test() -> Self {
-        Self {
-            faces: Vec::from([
-                Face {
-                    bounds: Bounds {
-                        x: (9..13),
-                        y: (1..5),
-                    },
-                },
-                Face {
-                    bounds: Bounds {
-                        x: (1..5),
-                        y: (5..9),
-                    },
-                },
-                Face {
-                    bounds: Bounds {
-                        x: (5..9),
-                        y: (5..9),
-                    },
-                },
-                Face {
-                    bounds: Bounds {
-                        x: (9..13),
-                        y: (5..9),
-                    },
-                },
-                Face {
-                    bounds: Bounds {
-                        x: (13..17),
-                        y: (9..13),
-                    },
-                },
-                Face {
-                    bounds: Bounds {
-                        x: (9..13),
-                        y: (9..13),
-                    },
-                },
-            ]),
+    fn fold(cells: &[Cell], width: usize, height: usize) -> Result<Self, InvalidNet> {
+        let area = cells.iter().filter(|c| **c != Cell::Void).count();
+        let size = ((area / 6) as f64).sqrt() as usize;
+        if size == 0 || size * size * 6 != area {
+            return Err(InvalidNet::Area(area));
         }
-    }
-
-    fn new_input() -> Self {
-        Self {
-            faces: Vec::from([
-                Face {
-                    bounds: Bounds {
-                        x: (51..101),
-                        y: (1..51),
-                    },
-                },
-                Face {
-                    bounds: Bounds {
-                        x: (1..51),
-                        y: (151..201),
-                    },
-                },
-                Face {
-                    bounds: Bounds {
-                        x: (1..51),
-                        y: (101..151),
-                    },
-                },
-                Face {
-                    bounds: Bounds {
-                        x: (51..101),
-                        y: (51..101),
-                    },
-                },
-                Face {
-                    bounds: Bounds {
-                        x: (101..151),
-                        y: (1..51),
-                    },
-                },
-                Face {
+        let mut tiles: Vec<(usize, usize)> = Vec::new();
+        for ty in 0..(height - 2) / size {
+            for tx in 0..(width - 2) / size {
+                if cells[(1 + ty * size) * width + 1 + tx * size] != Cell::Void {
+                    tiles.push((tx, ty));
+                }
+            }
+        }
+        if tiles.len() != 6 {
+            return Err(InvalidNet::FaceCount(tiles.len()));
+        }
+        let mut orientations: Vec<Option<(Vec3, Vec3, Vec3)>> = vec![None; tiles.len()];
+        orientations[0] = Some((
+            Vec3 { x: 0, y: 0, z: -1 },
+            Vec3 { x: 1, y: 0, z: 0 },
+            Vec3 { x: 0, y: 1, z: 0 },
+        ));
+        let mut queue = vec![0];
+        while let Some(current) = queue.pop() {
+            let (tx, ty) = tiles[current];
+            let (normal, right, down) = orientations[current].unwrap();
+            let neighbours = [
+                ((tx + 1, ty), (right, -normal, down)),
+                ((tx, ty + 1), (down, right, -normal)),
+                ((tx.wrapping_sub(1), ty), (-right, normal, down)),
+                ((tx, ty.wrapping_sub(1)), (-down, right, normal)),
+            ];
+            for (tile, orientation) in neighbours {
+                if let Some(next) = tiles.iter().position(|t| *t == tile) {
+                    if orientations[next].is_none() {
+                        orientations[next] = Some(orientation);
+                        queue.push(next);
+                    }
+                }
+            }
+        }
+        let faces: Vec<Face> = tiles
+            .iter()
+            .zip(orientations)
+            .map(|((tx, ty), orientation)| {
+                let (normal, right, down) = orientation.ok_or(InvalidNet::Disconnected)?;
+                Ok(Face {
                     bounds: Bounds {
-                        x: (51..101),
-                        y: (101..151),
+                        x: (1 + tx * size..1 + (tx + 1) * size),
+                        y: (1 + ty * size..1 + (ty + 1) * size),
                     },
-                },
-            ]),
+                    normal,
+                    right,
+                    down,
+                })
+            })
+            .collect::<Result<Vec<Face>, InvalidNet>>()?;
+        for (i, face) in faces.iter().enumerate() {
+            if faces[..i].iter().any(|f| f.normal == face.normal) {
+                return Err(InvalidNet::OverlappingFaces);
+            }
         }
+        Ok(Self { faces, size })
     }
 
     fn cube_index(&self, pos: &Pos) -> Option<u8> {
         self.faces
             .iter()
             .position(|f| f.bounds.contains(pos))
-            .and_then(|index| Some((index + 1) as u8))
+            .map(|index| (index + 1) as u8)
     }
 
     fn get_face(&self, number: u8) -> &Face {
@@ -200,84 +228,162 @@ impl Cube {
     }
 
     fn from(&self, from: &Pos, direction: &Direction) -> (Pos, Direction) {
-        println!("Searching face for {:?}", from);
         let face_nb = self.cube_index(from).unwrap();
         let face = self.get_face(face_nb);
-        let position = match direction {
-            Direction::Bottom | Direction::Top => from.x - face.bounds.x.start,
-            Direction::Left | Direction::Right => from.y - face.bounds.y.start,
+        let outwards = face.direction(direction);
+        let destination_face_nb = self
+            .faces
+            .iter()
+            .position(|f| f.normal == outwards)
+            .unwrap() as u8
+            + 1;
+        let destination_face = self.get_face(destination_face_nb);
+        // Once over the edge we keep going away from the face we left.
+        let travel = -face.normal;
+        let new_direction = [
+            Direction::Right,
+            Direction::Bottom,
+            Direction::Left,
+            Direction::Top,
+        ]
+        .into_iter()
+        .find(|d| destination_face.direction(d) == travel)
+        .unwrap();
+        let (position, along) = match direction {
+            Direction::Bottom | Direction::Top => (from.x - face.bounds.x.start, face.right),
+            Direction::Left | Direction::Right => (from.y - face.bounds.y.start, face.down),
         };
-        let (destination_face_nb, new_direction) = match direction {
-            Direction::Bottom => Self::normal_down(face_nb),
-            Direction::Left => Self::normal_left(face_nb),
-            Direction::Right => Self::normal_right(face_nb),
-            Direction::Top => Self::normal_up(face_nb),
+        let new_along = match new_direction {
+            Direction::Bottom | Direction::Top => destination_face.right,
+            Direction::Left | Direction::Right => destination_face.down,
+        };
+        let position = if new_along == along {
+            position
+        } else {
+            self.size - 1 - position
         };
-        let destination_face = self.get_face(destination_face_nb);
-        println!(
-            "Moving from cube {:?} facing {:?} to cube {:?} facing {:?}",
-            face_nb, direction, destination_face_nb, new_direction
-        );
         (
-            destination_face.at(position, direction, &new_direction),
+            destination_face.entry(position, &new_direction),
             new_direction,
         )
     }
+}
 
-    fn normal_up(from: u8) -> (u8, Direction) {
-        match from {
-            1 => (2, Direction::Right),
-            2 => (3, Direction::Top),
-            3 => (4, Direction::Right),
-            4 => (1, Direction::Top),
-            5 => (2, Direction::Top),
-            6 => (4, Direction::Top),
-            _ => panic!("Invalid index"),
-        }
+trait WrapStrategy {
+    // Where the player ends up when walking off the map from `from`.
+    fn wrap(&self, from: &Pos, direction: &Direction) -> (Pos, Direction);
+
+    fn face(&self, _pos: &Pos) -> Option<u8> {
+        None
     }
+}
 
-    fn normal_down(from: u8) -> (u8, Direction) {
-        match from {
-            1 => (4, Direction::Bottom),
-            2 => (5, Direction::Bottom),
-            3 => (2, Direction::Bottom),
-            4 => (6, Direction::Bottom),
-            5 => (4, Direction::Left),
-            6 => (2, Direction::Left),
-            _ => panic!("Invalid index"),
-        }
+impl WrapStrategy for Cube {
+    fn wrap(&self, from: &Pos, direction: &Direction) -> (Pos, Direction) {
+        self.from(from, direction)
+    }
+
+    fn face(&self, pos: &Pos) -> Option<u8> {
+        self.cube_index(pos)
+    }
+}
+
+// Wraps around to the other end of the current row or column.
+struct FlatWrap {
+    rows: Vec<Range<usize>>,
+    columns: Vec<Range<usize>>,
+}
+
+impl FlatWrap {
+    fn new(cells: &[Cell], width: usize, height: usize) -> Self {
+        let rows = (0..height)
+            .map(|y| Self::span((0..width).filter(|x| cells[y * width + x] != Cell::Void)))
+            .collect();
+        let columns = (0..width)
+            .map(|x| Self::span((0..height).filter(|y| cells[y * width + x] != Cell::Void)))
+            .collect();
+        Self { rows, columns }
     }
 
-    fn normal_left(from: u8) -> (u8, Direction) {
-        match from {
-            1 => (3, Direction::Right),
-            2 => (1, Direction::Bottom),
-            3 => (1, Direction::Right),
-            4 => (3, Direction::Bottom),
-            5 => (1, Direction::Left),
-            6 => (3, Direction::Left),
-            _ => panic!("Invalid index"),
+    fn span(mut filled: impl Iterator<Item = usize>) -> Range<usize> {
+        match filled.next() {
+            Some(start) => start..filled.last().unwrap_or(start) + 1,
+            None => 0..0,
         }
     }
+}
+
+impl WrapStrategy for FlatWrap {
+    fn wrap(&self, from: &Pos, direction: &Direction) -> (Pos, Direction) {
+        let (row, column) = (&self.rows[from.y], &self.columns[from.x]);
+        let destination = match direction {
+            Direction::Right => Pos {
+                x: row.start,
+                y: from.y,
+            },
+            Direction::Left => Pos {
+                x: row.end - 1,
+                y: from.y,
+            },
+            Direction::Bottom => Pos {
+                x: from.x,
+                y: column.start,
+            },
+            Direction::Top => Pos {
+                x: from.x,
+                y: column.end - 1,
+            },
+        };
+        (destination, *direction)
+    }
+}
 
-    fn normal_right(from: u8) -> (u8, Direction) {
-        match from {
-            1 => (5, Direction::Right),
-            2 => (6, Direction::Top),
-            3 => (6, Direction::Right),
-            4 => (5, Direction::Top),
-            5 => (6, Direction::Left),
-            6 => (5, Direction::Left),
-            _ => panic!("Invalid index"),
+#[derive(Clone, Copy, Debug)]
+enum WrapMode {
+    Flat,
+    Cube,
+}
+
+impl FromStr for WrapMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "flat" => Ok(Self::Flat),
+            "cube" => Ok(Self::Cube),
+            _ => Err(format!("Unknown wrap mode {}", s)),
         }
     }
 }
 
 struct Blocked {}
 
+struct JournalEntry {
+    action: Action,
+    position: Pos,
+    facing: Direction,
+    face: Option<u8>,
+}
+
+impl Display for JournalEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?}: at ({}, {}) facing {:?}",
+            self.action, self.position.x, self.position.y, self.facing
+        )?;
+        match self.face {
+            Some(face) => write!(f, " on face {}", face),
+            None => Ok(()),
+        }
+    }
+}
+
 struct World {
     cells: Vec<Cell>,
-    cube: Cube,
+    journal: Vec<JournalEntry>,
+    trail: Vec<(Pos, Direction)>,
+    wrap: Box<dyn WrapStrategy>,
     width: usize,
     height: usize,
     player_dir: Direction,
@@ -285,26 +391,32 @@ struct World {
 }
 
 impl World {
-    fn new(cells: Vec<Vec<Cell>>) -> Self {
-        let width = cells.iter().map(|a| a.len()).max().or(Some(0)).unwrap() + 2;
+    fn new(cells: Vec<Vec<Cell>>, mode: WrapMode) -> Result<Self, InvalidNet> {
+        let width = cells.iter().map(|a| a.len()).max().unwrap_or(0) + 2;
         let height = cells.len() + 2;
         let mut final_cells = Vec::with_capacity(width * height);
         final_cells.extend((0..width).map(|_| Cell::Void));
-        for (y, line) in cells.iter().enumerate() {
+        for line in cells.iter() {
             final_cells.push(Cell::Void);
             final_cells.extend_from_slice(line);
             final_cells.extend((0..(width - line.len() - 1)).map(|_| Cell::Void));
         }
         final_cells.extend((0..width).map(|_| Cell::Void));
         let first_x = final_cells.iter().position(|c| c == &Cell::Open).unwrap() - width;
-        Self {
+        let wrap: Box<dyn WrapStrategy> = match mode {
+            WrapMode::Flat => Box::new(FlatWrap::new(&final_cells, width, height)),
+            WrapMode::Cube => Box::new(Cube::fold(&final_cells, width, height)?),
+        };
+        Ok(Self {
             width,
             height,
             cells: final_cells,
             player_dir: Direction::Right,
             player_pos: Pos { x: first_x, y: 1 },
-            cube: Cube::new_input(),
-        }
+            journal: Vec::new(),
+            trail: vec![(Pos { x: first_x, y: 1 }, Direction::Right)],
+            wrap,
+        })
     }
 
     fn index(&self, x: usize, y: usize) -> usize {
@@ -325,13 +437,9 @@ impl World {
                 Ok(())
             }
             Some(Cell::Void) => {
-                let (new_dest, new_dir) = self.cube.from(&self.player_pos, &self.player_dir);
+                let (new_dest, new_dir) = self.wrap.wrap(&self.player_pos, &self.player_dir);
                 assert_ne!(new_dest, destination);
-                self.move_to(new_dest).and_then(|_| {
-                    self.player_dir = new_dir;
-                    println!("Now at {:?} facing {:?}", self.player_pos, self.player_dir);
-                    Ok(())
-                })
+                self.move_to(new_dest).map(|_| self.player_dir = new_dir)
             }
             Some(Cell::Wall) => Err(Blocked {}),
             _ => panic!("Unexpected destination"),
@@ -347,9 +455,8 @@ impl World {
                 Direction::Right => (1, 0),
             };
             let destination = &self.player_pos + &offset;
-            println!("Moving to {:?}", destination);
             match self.move_to(destination) {
-                Ok(_) => {}
+                Ok(_) => self.trail.push((self.player_pos, self.player_dir)),
                 Err(_) => break,
             }
         }
@@ -373,21 +480,56 @@ impl World {
         }
     }
 
-    fn print(&self) {
-        for y in 0..self.height {
-            let line: String = (0..self.width)
-                .map(|x| match self.cells[self.index(x, y)] {
-                    Cell::Open => '.',
-                    Cell::Void => ' ',
-                    Cell::Wall => '#',
-                })
-                .collect();
-            println!("{}", line);
+    fn perform(&mut self, action: &Action) {
+        match action {
+            Action::Move(distance) => self.move_player(*distance as usize),
+            Action::TurnAntiClockwise => self.turn_counter_clockwize(),
+            Action::TurnClockwise => self.turn_clockwise(),
         }
+        self.trail.push((self.player_pos, self.player_dir));
+        self.journal.push(JournalEntry {
+            action: action.clone(),
+            position: self.player_pos,
+            facing: self.player_dir,
+            face: self.wrap.face(&self.player_pos),
+        });
+    }
+
+    fn password(&self) -> usize {
+        1000 * self.player_pos.y + 4 * self.player_pos.x + self.player_dir.score()
+    }
+
+    // The map with the last facing of the player drawn on every visited tile.
+    fn render(&self) -> String {
+        let mut tiles: Vec<char> = self
+            .cells
+            .iter()
+            .map(|c| match c {
+                Cell::Open => '.',
+                Cell::Void => ' ',
+                Cell::Wall => '#',
+            })
+            .collect();
+        for (pos, direction) in self.trail.iter() {
+            tiles[self.index(pos.x, pos.y)] = match direction {
+                Direction::Right => '>',
+                Direction::Bottom => 'v',
+                Direction::Left => '<',
+                Direction::Top => '^',
+            };
+        }
+        (1..self.height - 1)
+            .map(|y| {
+                let line: String = tiles[self.index(1, y)..self.index(self.width - 1, y)]
+                    .iter()
+                    .collect();
+                format!("{}\n", line.trim_end())
+            })
+            .collect()
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum Action {
     Move(u32),
     TurnClockwise,
@@ -403,14 +545,12 @@ fn layout_line(input: &str) -> IResult<&str, Vec<Cell>> {
     }))(input)
 }
 
-fn world(input: &str) -> IResult<&str, World> {
-    let (input, lines) = separated_list1(line_ending, layout_line)(input)?;
-    let world = World::new(lines);
-    Ok((input, world))
+fn layout(input: &str) -> IResult<&str, Vec<Vec<Cell>>> {
+    separated_list1(line_ending, layout_line)(input)
 }
 
 fn move_action(input: &str) -> IResult<&str, Action> {
-    map(number, |n| Action::Move(n))(input)
+    map(number, Action::Move)(input)
 }
 
 fn rotate_action(input: &str) -> IResult<&str, Action> {
@@ -425,38 +565,172 @@ fn directions(input: &str) -> IResult<&str, Vec<Action>> {
     many1(alt((move_action, rotate_action)))(input)
 }
 
-fn program(input: &str) -> IResult<&str, (World, Vec<Action>)> {
-    separated_pair(world, multispace1, directions)(input)
+fn program(input: &str) -> IResult<&str, (Vec<Vec<Cell>>, Vec<Action>)> {
+    separated_pair(layout, multispace1, directions)(input)
 }
 
 fn main() {
+    let usage = "Usage: dec22 [--wrap flat|cube]... [--verbose] [--journal <file>]";
+    let mut modes = Vec::new();
+    let mut verbose = false;
+    let mut journal_path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--wrap" => modes.push(args.next().expect(usage).parse().expect(usage)),
+            "--verbose" => verbose = true,
+            "--journal" => journal_path = Some(args.next().expect(usage)),
+            _ => panic!("{}", usage),
+        }
+    }
+    if modes.is_empty() {
+        modes = vec![WrapMode::Flat, WrapMode::Cube];
+    }
     let mut file = File::open("./src/input.txt").unwrap();
     let mut content = String::new();
     file.read_to_string(&mut content).unwrap();
-    let (_, (mut world, actions)) = program(&content).unwrap();
-    println!(
-        "position {:?}, facing {:?}",
-        world.player_pos, world.player_dir
-    );
-    for action in actions {
-        println!();
-        println!("{:?}", action);
-        match action {
-            Action::Move(distance) => world.move_player(distance as usize),
-            Action::TurnAntiClockwise => world.turn_counter_clockwize(),
-            Action::TurnClockwise => world.turn_clockwise(),
+    let (_, (layout, actions)) = program(&content).unwrap();
+    let mut export = String::new();
+    for mode in modes {
+        let mut world = match World::new(layout.clone(), mode) {
+            Ok(world) => world,
+            Err(e) => {
+                println!("The map can not be folded into a cube: {}", e);
+                continue;
+            }
+        };
+        for action in actions.iter() {
+            world.perform(action);
+            if verbose {
+                println!("{}", world.journal.last().unwrap());
+            }
         }
-        println!(
-            "position {:?}, facing {:?}",
-            world.player_pos, world.player_dir
-        );
-    }
-    let final_pos = world.player_pos;
-    let final_direction = world.player_dir;
-    println!(
-        "Final position {:?}, facing {:?}",
-        final_pos, final_direction
-    );
-    let password = 1000 * (final_pos.y) + 4 * (final_pos.x) + final_direction.score();
-    println!("Password is {}", password);
+        println!("Password with {:?} wrapping is {}", mode, world.password());
+        export.push_str(&format!(
+            "== {:?} wrapping ==\n\n{}\n",
+            mode,
+            world.render()
+        ));
+        for entry in world.journal.iter() {
+            export.push_str(&format!("{}\n", entry));
+        }
+        export.push('\n');
+    }
+    if let Some(path) = journal_path {
+        fs::write(path, export).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{program, Cell, Cube, Direction, InvalidNet, Pos, World, WrapMode};
+
+    fn net_layout(net: &[&str], size: usize) -> Vec<Vec<Cell>> {
+        net.iter()
+            .flat_map(|row| {
+                let line: Vec<Cell> = row
+                    .chars()
+                    .flat_map(|c| {
+                        let cell = match c {
+                            '#' => Cell::Open,
+                            _ => Cell::Void,
+                        };
+                        vec![cell; size]
+                    })
+                    .collect();
+                vec![line; size]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_example() {
+        let content = fs::read_to_string("./src/test.txt").unwrap();
+        let (_, (layout, actions)) = program(&content).unwrap();
+        let mut world = World::new(layout.clone(), WrapMode::Flat).unwrap();
+        actions.iter().for_each(|a| world.perform(a));
+        assert_eq!(world.password(), 6032);
+        let mut world = World::new(layout, WrapMode::Cube).unwrap();
+        actions.iter().for_each(|a| world.perform(a));
+        assert_eq!(world.password(), 5031);
+        assert_eq!(world.journal.len(), 13);
+        // The second forward move wraps from face 4 down onto face 6.
+        let wrapped = &world.journal[4];
+        assert_eq!(world.journal[3].face, Some(4));
+        assert_eq!(wrapped.face, Some(6));
+        assert_eq!(wrapped.position, Pos { x: 15, y: 11 });
+        assert_eq!(wrapped.facing, Direction::Bottom);
+        let map = world.render();
+        let rows: Vec<&str> = map.lines().collect();
+        assert_eq!(rows[0], "        >>v#");
+        assert_eq!(rows[5], ".>>>>>^.#.>>");
+        assert_eq!(rows[10], "        .#v<<<<.");
+    }
+
+    #[test]
+    fn test_all_nets_glue_back() {
+        let nets = [
+            vec!["#...", "####", "#..."],
+            vec!["#...", "####", ".#.."],
+            vec!["#...", "####", "..#."],
+            vec!["#...", "####", "...#"],
+            vec![".#..", "####", ".#.."],
+            vec![".#..", "####", "..#."],
+            vec!["##..", ".###", ".#.."],
+            vec!["##..", ".###", "..#."],
+            vec!["##..", ".###", "...#"],
+            vec!["##..", ".##.", "..##"],
+            vec!["###..", "..###"],
+        ];
+        let size = 3;
+        let opposite = |d: Direction| match d {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::Top => Direction::Bottom,
+            Direction::Bottom => Direction::Top,
+        };
+        for net in nets {
+            let world = World::new(net_layout(&net, size), WrapMode::Cube).unwrap();
+            let cube = Cube::fold(&world.cells, world.width, world.height).unwrap();
+            for y in 0..world.height {
+                for x in 0..world.width {
+                    let pos = Pos { x, y };
+                    if world.at(&pos) != Some(&Cell::Open) {
+                        continue;
+                    }
+                    for direction in [
+                        Direction::Left,
+                        Direction::Right,
+                        Direction::Top,
+                        Direction::Bottom,
+                    ] {
+                        let offset = match direction {
+                            Direction::Bottom => (0, 1),
+                            Direction::Left => (-1, 0),
+                            Direction::Top => (0, -1),
+                            Direction::Right => (1, 0),
+                        };
+                        if world.at(&(&pos + &offset)) != Some(&Cell::Void) {
+                            continue;
+                        }
+                        let (landing, new_direction) = cube.from(&pos, &direction);
+                        assert_eq!(world.at(&landing), Some(&Cell::Open));
+                        let (back, back_direction) = cube.from(&landing, &opposite(new_direction));
+                        assert_eq!(back, pos, "net {:?}", net);
+                        assert_eq!(back_direction, opposite(direction));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_nets() {
+        let disconnected = World::new(net_layout(&["##.#", "###."], 2), WrapMode::Cube);
+        assert!(matches!(disconnected, Err(InvalidNet::Disconnected)));
+        let too_few = World::new(net_layout(&["###", "##."], 2), WrapMode::Cube);
+        assert!(matches!(too_few, Err(InvalidNet::Area(20))));
+    }
 }
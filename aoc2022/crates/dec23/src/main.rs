@@ -1,8 +1,10 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::{collections::VecDeque, ops::Add};
+use std::ops::Add;
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 struct Pos {
     x: i32,
     y: i32,
@@ -19,12 +21,59 @@ impl Add<(i32, i32)> for Pos {
     }
 }
 
-#[derive(Debug)]
-enum Direction {
-    Left,
-    Right,
-    Up,
-    Down,
+// An elf proposes to move by `step` if none of the `checks` offsets is taken.
+struct Proposal {
+    step: (i32, i32),
+    checks: Vec<(i32, i32)>,
+}
+
+struct Rules {
+    // An elf with none of these offsets taken stays put.
+    neighbours: Vec<(i32, i32)>,
+    // Considered in order, the first one shifting to the back every round.
+    proposals: Vec<Proposal>,
+}
+
+impl Rules {
+    fn standard() -> Self {
+        Self::with_order("NSWE").unwrap()
+    }
+
+    // Builds the rules from the order in which the N, S, W and E directions
+    // are considered. Each direction checks the three tiles in front of it.
+    fn with_order(order: &str) -> Option<Self> {
+        let proposals = order
+            .chars()
+            .map(|c| {
+                let step = match c {
+                    'N' => (0, -1),
+                    'S' => (0, 1),
+                    'W' => (-1, 0),
+                    'E' => (1, 0),
+                    _ => return None,
+                };
+                let side = (step.1, step.0);
+                Some(Proposal {
+                    step,
+                    checks: Vec::from([
+                        (step.0 - side.0, step.1 - side.1),
+                        step,
+                        (step.0 + side.0, step.1 + side.1),
+                    ]),
+                })
+            })
+            .collect::<Option<Vec<Proposal>>>()?;
+        if proposals.is_empty() {
+            return None;
+        }
+        Some(Self {
+            neighbours: (-1..=1)
+                .flat_map(|y| (-1..=1).map(move |x| (x, y)))
+                .filter(|offset| *offset != (0, 0))
+                .collect(),
+            proposals,
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -35,20 +84,22 @@ struct Bounds {
     west: i32,
 }
 
-impl Bounds {
-    fn contains(&self, pos: &Pos) -> bool {
-        pos.x >= self.west && pos.x <= self.east && pos.y <= self.south && pos.y >= self.north
-    }
-}
-
 struct Game {
     elves: Vec<Pos>,
+    occupied: HashSet<Pos>,
+    rules: Rules,
     tick: u32,
 }
 
 impl Game {
-    fn new(elves: Vec<Pos>) -> Self {
-        Self { elves, tick: 0 }
+    fn new(elves: Vec<Pos>, rules: Rules) -> Self {
+        let occupied = elves.iter().copied().collect();
+        Self {
+            elves,
+            occupied,
+            rules,
+            tick: 0,
+        }
     }
 
     fn bounds(&self) -> Bounds {
@@ -73,7 +124,7 @@ impl Game {
                 bounds.south = elf.y
             }
         }
-        return bounds;
+        bounds
     }
 
     fn empty_in_bounds(&self) -> i32 {
@@ -82,134 +133,85 @@ impl Game {
         size - self.elves.len() as i32
     }
 
-    fn is_elve_around(&self, pos: Pos) -> bool {
-        self.elves.iter().any(|other| {
-            i32::abs_diff(pos.x, other.x) <= 1
-                && i32::abs_diff(pos.y, other.y) <= 1
-                && *other != pos
-        })
-    }
-
-    fn is_elve_north(&self, pos: Pos) -> bool {
-        self.elves
-            .iter()
-            .any(|other| i32::abs_diff(pos.x, other.x) <= 1 && other.y - pos.y == -1)
-    }
-
-    fn is_elve_south(&self, pos: Pos) -> bool {
-        self.elves
-            .iter()
-            .any(|other| i32::abs_diff(pos.x, other.x) <= 1 && other.y - pos.y == 1)
-    }
-
-    fn is_elve_west(&self, pos: Pos) -> bool {
-        self.elves
-            .iter()
-            .any(|other| i32::abs_diff(pos.y, other.y) <= 1 && other.x - pos.x == -1)
+    fn is_free(&self, pos: Pos, offsets: &[(i32, i32)]) -> bool {
+        offsets.iter().all(|o| !self.occupied.contains(&(pos + *o)))
     }
 
-    fn is_elve_east(&self, pos: Pos) -> bool {
-        self.elves
-            .iter()
-            .any(|other| i32::abs_diff(pos.y, other.y) <= 1 && other.x - pos.x == 1)
-    }
-
-    fn make_decision(&mut self) -> Vec<Option<Pos>> {
-        let mut possibilities = VecDeque::from([
-            Direction::Up,
-            Direction::Down,
-            Direction::Left,
-            Direction::Right,
-        ]);
-        possibilities.rotate_left((self.tick % 4) as usize);
+    fn make_decision(&self) -> Vec<Option<Pos>> {
+        let count = self.rules.proposals.len();
+        let first = self.tick as usize % count;
         self.elves
             .iter()
             .map(|pos| {
-                let mut destination: Option<Pos> = None;
-                if !self.is_elve_around(*pos) {
+                if self.is_free(*pos, &self.rules.neighbours) {
                     return None;
                 }
-                'pos: for possibility in possibilities.iter() {
-                    let can_do = match possibility {
-                        Direction::Up => !self.is_elve_north(*pos),
-                        Direction::Down => !self.is_elve_south(*pos),
-                        Direction::Left => !self.is_elve_west(*pos),
-                        Direction::Right => !self.is_elve_east(*pos),
-                    };
-
-                    if can_do {
-                        destination = Some(match possibility {
-                            Direction::Up => *pos + (0, -1),
-                            Direction::Down => *pos + (0, 1),
-                            Direction::Left => *pos + (-1, 0),
-                            Direction::Right => *pos + (1, 0),
-                        });
-                        break 'pos;
-                    }
-                }
-                destination
+                (0..count)
+                    .map(|i| &self.rules.proposals[(first + i) % count])
+                    .find(|proposal| self.is_free(*pos, &proposal.checks))
+                    .map(|proposal| *pos + proposal.step)
             })
             .collect()
     }
 
-    fn make_movements(&mut self, mut movements: Vec<Option<Pos>>) -> usize {
-        let length = movements.len();
-        for i in 0..length {
-            let first = movements[i];
-            match first {
-                None => {}
-                Some(_) if i < length - 1 => {
-                    for j in (i + 1)..length {
-                        if movements[j] == first {
-                            movements[j] = None;
-                            movements[i] = None;
-                        }
-                    }
+    fn make_movements(&mut self, movements: Vec<Option<Pos>>) -> usize {
+        let mut proposals: HashMap<Pos, usize> = HashMap::new();
+        for destination in movements.iter().flatten() {
+            *proposals.entry(*destination).or_default() += 1;
+        }
+        let mut moves = 0;
+        for (i, movement) in movements.into_iter().enumerate() {
+            match movement {
+                Some(pos) if proposals[&pos] == 1 => {
+                    self.occupied.remove(&self.elves[i]);
+                    self.occupied.insert(pos);
+                    self.elves[i] = pos;
+                    moves += 1;
                 }
                 _ => {}
             }
         }
-        movements
-            .iter()
-            .enumerate()
-            .for_each(|(i, movement)| match movement {
-                Some(pos) => self.elves[i] = *pos,
-                None => {}
-            });
-        return movements.iter().filter(|m| m.is_some()).count();
+        moves
     }
 
     fn round(&mut self) -> usize {
         let movements = self.make_decision();
         let moves = self.make_movements(movements);
         self.tick += 1;
-        return moves;
+        moves
     }
 
-    fn run_to_completion(&mut self) {
-        loop {
-            println!("ROUND {}", self.tick + 1);
+    // Plays until no elf moves or `limit` rounds have been played, calling
+    // `observe` before the first round and after every round. Returns the
+    // first round in which no elf moved, if it was reached.
+    fn run(&mut self, limit: Option<u32>, mut observe: impl FnMut(&Game)) -> Option<u32> {
+        observe(self);
+        while limit.is_none_or(|l| self.tick < l) {
             let moves = self.round();
+            observe(self);
             if moves == 0 {
-                break;
+                return Some(self.tick);
             }
         }
+        None
     }
 
-    fn print(&self) {
+    fn render(&self) -> String {
         let bounds = self.bounds();
-        for y in bounds.north..bounds.south + 1 {
-            let line: String = (bounds.west..bounds.east + 1)
-                .map(|x| {
-                    if self.elves.iter().any(|p| *p == Pos { x, y }) {
-                        '#'
-                    } else {
-                        '.'
-                    }
-                })
-                .collect();
-            println!("{}", line);
-        }
+        (bounds.north..bounds.south + 1)
+            .map(|y| {
+                let line: String = (bounds.west..bounds.east + 1)
+                    .map(|x| {
+                        if self.occupied.contains(&Pos { x, y }) {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect();
+                format!("{}\n", line)
+            })
+            .collect()
     }
 }
 
@@ -220,21 +222,83 @@ fn main() {
     for (y, line) in lines.enumerate() {
         let content = line.unwrap();
         for (x, c) in content.char_indices() {
-            match c {
-                '#' => elves.push(Pos {
+            if c == '#' {
+                elves.push(Pos {
                     x: x as i32,
                     y: y as i32,
-                }),
-                _ => {}
+                });
             }
         }
     }
     println!("{} elves on the map", elves.len());
-    let mut game = Game::new(elves);
-    game.run_to_completion();
-    println!(
-        "{} empty ground tiles after {} rounds",
-        game.empty_in_bounds(),
-        game.tick
-    );
+    let usage = "Usage: dec23 [--order NSWE] [--rounds <count>] [--snapshot <round>]...";
+    let mut rules = Rules::standard();
+    let mut limit = None;
+    let mut snapshots = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().expect(usage);
+        match arg.as_str() {
+            "--order" => rules = Rules::with_order(&value).expect(usage),
+            "--rounds" => limit = Some(value.parse().expect(usage)),
+            "--snapshot" => snapshots.push(value.parse::<u32>().expect(usage)),
+            _ => panic!("{}", usage),
+        }
+    }
+    let mut game = Game::new(elves, rules);
+    let mut empty_after_ten = None;
+    let settled = game.run(limit, |game| {
+        if game.tick == 10 {
+            empty_after_ten = Some(game.empty_in_bounds());
+        }
+        if snapshots.contains(&game.tick) {
+            println!("== End of round {} ==\n{}", game.tick, game.render());
+        }
+    });
+    match empty_after_ten.or(settled.map(|_| game.empty_in_bounds())) {
+        Some(empty) => println!("{} empty ground tiles after 10 rounds", empty),
+        None => println!("Stopped before round 10"),
+    }
+    match settled {
+        Some(round) => println!("No elf moved during round {}", round),
+        None => println!(
+            "Elves are still moving after {} rounds, with {} empty ground tiles",
+            game.tick,
+            game.empty_in_bounds()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{Game, Pos, Rules};
+
+    #[test]
+    fn test_example() {
+        let elves = fs::read_to_string("./src/test.txt")
+            .unwrap()
+            .lines()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.char_indices()
+                    .filter(|(_, c)| *c == '#')
+                    .map(move |(x, _)| Pos {
+                        x: x as i32,
+                        y: y as i32,
+                    })
+                    .collect::<Vec<Pos>>()
+            })
+            .collect();
+        let mut game = Game::new(elves, Rules::standard());
+        let mut empty_after_ten = None;
+        let settled = game.run(None, |game| {
+            if game.tick == 10 {
+                empty_after_ten = Some(game.empty_in_bounds());
+            }
+        });
+        assert_eq!(empty_after_ten, Some(110));
+        assert_eq!(settled, Some(20));
+    }
 }
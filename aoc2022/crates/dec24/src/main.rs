@@ -1,5 +1,6 @@
-use std::collections::HashSet;
-use std::fs::File;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
 use std::ops::Add;
 
@@ -33,12 +34,6 @@ impl Add<(i32, i32)> for Pos {
     }
 }
 
-impl Pos {
-    fn distance(&self, other: &Pos) -> u32 {
-        i32::abs_diff(self.x, other.x) + i32::abs_diff(self.y, other.y)
-    }
-}
-
 #[derive(Clone, Copy)]
 enum Direction {
     Up,
@@ -52,164 +47,231 @@ struct Blizzard {
     direction: Direction,
 }
 
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// Starting cells of the blizzards, by row for the horizontal ones and by
+// column for the vertical ones, so that whether a cell is taken at a given
+// minute is a lookup shifted by that minute.
+struct Occupancy {
+    right: Vec<Vec<bool>>,
+    left: Vec<Vec<bool>>,
+    down: Vec<Vec<bool>>,
+    up: Vec<Vec<bool>>,
+}
+
+struct Route {
+    // Minute at which each waypoint is reached.
+    arrivals: Vec<usize>,
+    // Position of the expedition at every minute.
+    positions: Vec<Pos>,
+}
+
 struct World {
     blizzards: Vec<Blizzard>,
-    scenarios: Vec<Pos>,
+    entrance: Pos,
+    exit: Pos,
+    occupancy: Occupancy,
     bounds: Bounds,
-    destination: Pos,
-    origin: Pos,
-    turns: u32,
+    width: usize,
+    height: usize,
+    period: usize,
 }
 
 impl World {
     fn new(blizzards: Vec<Blizzard>, bounds: Bounds) -> Self {
-        let destination = Pos {
-            y: bounds.south + 1,
-            x: bounds.east,
+        let width = (bounds.east - bounds.west + 1) as usize;
+        let height = (bounds.south - bounds.north + 1) as usize;
+        let mut occupancy = Occupancy {
+            right: vec![vec![false; width]; height],
+            left: vec![vec![false; width]; height],
+            down: vec![vec![false; height]; width],
+            up: vec![vec![false; height]; width],
         };
-        let origin = Pos { x: 1, y: 0 };
+        for blizzard in blizzards.iter() {
+            let x = (blizzard.pos.x - bounds.west) as usize;
+            let y = (blizzard.pos.y - bounds.north) as usize;
+            match blizzard.direction {
+                Direction::Right => occupancy.right[y][x] = true,
+                Direction::Left => occupancy.left[y][x] = true,
+                Direction::Down => occupancy.down[x][y] = true,
+                Direction::Up => occupancy.up[x][y] = true,
+            }
+        }
         Self {
             blizzards,
+            entrance: Pos {
+                x: bounds.west,
+                y: bounds.north - 1,
+            },
+            exit: Pos {
+                x: bounds.east,
+                y: bounds.south + 1,
+            },
+            occupancy,
             bounds,
-            scenarios: Vec::from([origin]),
-            origin,
-            destination,
-            turns: 0,
+            width,
+            height,
+            period: width * height / gcd(width, height),
         }
     }
 
-    fn print(&self) {
-        for y in self.bounds.north..self.bounds.south + 1 {
-            let line: String = (self.bounds.west..self.bounds.east + 1)
-                .map(|x| {
-                    let on_cell: Vec<&Blizzard> = self
-                        .blizzards
-                        .iter()
-                        .filter(|b| b.pos == Pos { x, y })
-                        .collect();
-                    match on_cell.len() {
-                        0 => ".".to_string(),
-                        1 => match on_cell[0].direction {
-                            Direction::Right => ">".to_string(),
-                            Direction::Left => "<".to_string(),
-                            Direction::Up => "^".to_string(),
-                            Direction::Down => "v".to_string(),
-                        },
-                        nb => nb.to_string(),
-                    }
-                })
-                .collect();
-            println!("{}", line);
+    fn is_free(&self, pos: &Pos, minute: usize) -> bool {
+        if !self.bounds.contains(pos) {
+            return true;
         }
+        let x = (pos.x - self.bounds.west) as usize;
+        let y = (pos.y - self.bounds.north) as usize;
+        let (w, h) = (self.width, self.height);
+        !(self.occupancy.right[y][(x + w - minute % w) % w]
+            || self.occupancy.left[y][(x + minute) % w]
+            || self.occupancy.down[x][(y + h - minute % h) % h]
+            || self.occupancy.up[x][(y + minute) % h])
     }
 
-    fn next_blizzards(&self) -> Vec<Blizzard> {
-        self.blizzards
-            .iter()
-            .map(|b| {
-                let pos = match b.direction {
-                    Direction::Up if b.pos.y > self.bounds.north => b.pos + (0, -1),
-                    Direction::Up => Pos {
-                        x: b.pos.x,
-                        y: self.bounds.south,
-                    },
-                    Direction::Down if b.pos.y < self.bounds.south => b.pos + (0, 1),
-                    Direction::Down => Pos {
-                        x: b.pos.x,
-                        y: self.bounds.north,
-                    },
-                    Direction::Right if b.pos.x < self.bounds.east => b.pos + (1, 0),
-                    Direction::Right => Pos {
-                        x: self.bounds.west,
-                        y: b.pos.y,
-                    },
-                    Direction::Left if b.pos.x > self.bounds.west => b.pos + (-1, 0),
-                    Direction::Left => Pos {
-                        x: self.bounds.east,
-                        y: b.pos.y,
-                    },
-                };
-                Blizzard {
-                    direction: b.direction,
-                    pos,
+    // Breadth-first search over (position, minute modulo the blizzard period),
+    // returning where the expedition stands every minute from `start` until
+    // `to` is first reached.
+    fn find_path(&self, from: Pos, to: Pos, start: usize) -> Option<Vec<Pos>> {
+        let mut seen = HashSet::from([(from, start % self.period)]);
+        let mut came_from: HashMap<(Pos, usize), Pos> = HashMap::new();
+        let mut frontier = Vec::from([from]);
+        let mut minute = start;
+        while !frontier.is_empty() {
+            if frontier.contains(&to) {
+                let mut route = Vec::from([to]);
+                for m in (start + 1..=minute).rev() {
+                    route.push(came_from[&(*route.last().unwrap(), m)]);
                 }
-            })
-            .collect()
+                route.reverse();
+                return Some(route);
+            }
+            minute += 1;
+            let mut next = Vec::new();
+            for current in frontier {
+                for offset in [(1, 0), (-1, 0), (0, 1), (0, -1), (0, 0)] {
+                    let pos = current + offset;
+                    let walkable = self.bounds.contains(&pos) || pos == from || pos == to;
+                    if walkable
+                        && self.is_free(&pos, minute)
+                        && seen.insert((pos, minute % self.period))
+                    {
+                        came_from.insert((pos, minute), current);
+                        next.push(pos);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        None
     }
 
-    fn taken_cells(&self) -> HashSet<Pos> {
-        self.blizzards.iter().map(|b| b.pos).collect()
+    // Goes through the waypoints in order, starting at minute 0.
+    fn trip(&self, waypoints: &[Pos]) -> Option<Route> {
+        let mut route = Route {
+            arrivals: Vec::from([0]),
+            positions: Vec::from([waypoints[0]]),
+        };
+        for leg in waypoints.windows(2) {
+            let start = *route.arrivals.last().unwrap();
+            let positions = self.find_path(leg[0], leg[1], start)?;
+            route.arrivals.push(start + positions.len() - 1);
+            route.positions.extend_from_slice(&positions[1..]);
+        }
+        Some(route)
     }
 
-    fn turn(&mut self) -> Option<u32> {
-        self.blizzards = self.next_blizzards();
-        let taken_cells = self.taken_cells();
-        let mut next_pos = HashSet::new();
-        for current in self.scenarios.iter() {
-            for offset in [(1, 0), (-1, 0), (0, 1), (0, -1), (0, 0)] {
-                let pos = *current + offset;
-                if taken_cells.contains(&pos)
-                    || (!self.bounds.contains(&pos)
-                        && pos != self.destination
-                        && pos != self.origin)
-                {
-                    continue;
-                }
-                next_pos.insert(pos);
-            }
-        }
-        let mut next_pos: Vec<Pos> = next_pos.into_iter().collect();
-        let min_distance = next_pos
-            .iter()
-            .map(|p| p.distance(&self.destination))
-            .min()
-            .or(Some(100))
-            .unwrap();
-        next_pos = next_pos
-            .into_iter()
-            .filter(|p| p.distance(&self.destination) <= min_distance + 18)
-            .collect();
-        self.scenarios = next_pos;
-        self.turns += 1;
-        if self.scenarios.len() == 0 {
-            panic!("No more valid scenario !");
+    fn blizzard_at(&self, blizzard: &Blizzard, minute: usize) -> Pos {
+        let x = (blizzard.pos.x - self.bounds.west) as usize;
+        let y = (blizzard.pos.y - self.bounds.north) as usize;
+        let (w, h) = (self.width, self.height);
+        let (x, y) = match blizzard.direction {
+            Direction::Right => ((x + minute) % w, y),
+            Direction::Left => ((x + w - minute % w) % w, y),
+            Direction::Down => (x, (y + minute) % h),
+            Direction::Up => (x, (y + h - minute % h) % h),
+        };
+        Pos {
+            x: self.bounds.west + x as i32,
+            y: self.bounds.north + y as i32,
         }
-        println!(
-            "Turn {}, {} positions still valid",
-            self.turns,
-            self.scenarios.len()
-        );
-        if min_distance == 0 {
-            Some(self.turns)
-        } else {
-            None
+    }
+
+    // The valley at `minute`, with the expedition drawn as `E`.
+    fn render(&self, minute: usize, expedition: &Pos) -> String {
+        let mut on_cell: HashMap<Pos, Vec<Direction>> = HashMap::new();
+        for blizzard in self.blizzards.iter() {
+            on_cell
+                .entry(self.blizzard_at(blizzard, minute))
+                .or_default()
+                .push(blizzard.direction);
         }
+        (self.bounds.north - 1..self.bounds.south + 2)
+            .map(|y| {
+                let line: String = (self.bounds.west - 1..self.bounds.east + 2)
+                    .map(|x| {
+                        let pos = Pos { x, y };
+                        if pos == *expedition {
+                            return "E".to_string();
+                        }
+                        if pos == self.entrance || pos == self.exit {
+                            return ".".to_string();
+                        }
+                        if !self.bounds.contains(&pos) {
+                            return "#".to_string();
+                        }
+                        match on_cell.get(&pos).map(|d| d.as_slice()) {
+                            None => ".".to_string(),
+                            Some([direction]) => match direction {
+                                Direction::Right => ">".to_string(),
+                                Direction::Left => "<".to_string(),
+                                Direction::Up => "^".to_string(),
+                                Direction::Down => "v".to_string(),
+                            },
+                            Some(directions) => directions.len().to_string(),
+                        }
+                    })
+                    .collect();
+                format!("{}\n", line)
+            })
+            .collect()
     }
 
-    fn find_path(&mut self, from: Pos, to: Pos) -> u32 {
-        self.destination = to;
-        self.origin = from;
-        self.turns = 0;
-        self.scenarios = Vec::from([self.origin]);
-        loop {
-            match self.turn() {
-                Some(res) => return res,
-                None => continue,
-            }
+    // Every frame of the route, saying how the expedition moved to get there.
+    fn replay(&self, route: &Route) -> String {
+        let mut frames = format!("Initial state:\n{}", self.render(0, &route.positions[0]));
+        for (minute, step) in route.positions.windows(2).enumerate() {
+            let action = match (step[1].x - step[0].x, step[1].y - step[0].y) {
+                (1, 0) => "move right",
+                (-1, 0) => "move left",
+                (0, 1) => "move down",
+                (0, -1) => "move up",
+                _ => "wait",
+            };
+            frames.push_str(&format!(
+                "\nMinute {}, {}:\n{}",
+                minute + 1,
+                action,
+                self.render(minute + 1, &step[1])
+            ));
         }
+        frames
     }
 }
 
-fn main() {
-    let file = File::open("./src/input.txt").unwrap();
-    let lines = BufReader::new(file).lines();
+// Reads the valley, returning it along with its entrance and exit.
+fn valley(lines: impl Iterator<Item = String>) -> (World, Pos, Pos) {
     let mut blizzards = Vec::new();
     let mut max_y = 0;
     let mut max_x: i32 = 0;
     for (y, line) in lines.enumerate() {
         max_y = y as i32;
-        let content = line.unwrap();
-        for (x, c) in content.char_indices() {
+        for (x, c) in line.char_indices() {
             if x as i32 > max_x {
                 max_x = x as i32;
             }
@@ -238,7 +300,7 @@ fn main() {
             }
         }
     }
-    let mut world = World::new(
+    let world = World::new(
         blizzards,
         Bounds {
             north: 1,
@@ -247,16 +309,71 @@ fn main() {
             east: max_x - 1,
         },
     );
-    let start = Pos { x: 1, y: 0 };
-    let end = Pos {
-        y: max_y,
-        x: max_x - 1,
+    let (start, end) = (world.entrance, world.exit);
+    (world, start, end)
+}
+
+fn main() {
+    let file = File::open("./src/input.txt").unwrap();
+    let lines = BufReader::new(file).lines();
+    let (world, start, end) = valley(lines.map(|l| l.unwrap()));
+    let mut replay = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--replay" => replay = Some(args.next()),
+            _ => panic!("Usage: dec24 [--replay [<file>]]"),
+        }
+    }
+    let route = match world.trip(&[start, end, start, end]) {
+        Some(route) => route,
+        None => return println!("The blizzards never let the expedition through"),
     };
-    let first_trip = world.find_path(start, end);
-    let back_trip = world.find_path(end, start);
-    let last_trip = world.find_path(start, end);
+    let legs: Vec<String> = route
+        .arrivals
+        .windows(2)
+        .map(|w| (w[1] - w[0]).to_string())
+        .collect();
     println!(
-        "It took {} turns to do the first trip, then {} back, finally {} to reach the end, for a total of {}",
-        first_trip, back_trip, last_trip, first_trip + back_trip + last_trip
+        "It took {} minutes for the legs of the trip, for a total of {}",
+        legs.join(", "),
+        route.arrivals.last().unwrap()
     );
+    match replay {
+        Some(Some(path)) => fs::write(path, world.replay(&route)).unwrap(),
+        Some(None) => print!("{}", world.replay(&route)),
+        None => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::valley;
+
+    #[test]
+    fn test_example() {
+        let content = fs::read_to_string("./src/test.txt").unwrap();
+        let (world, start, end) = valley(content.lines().map(String::from));
+        let route = world.trip(&[start, end, start, end]).unwrap();
+        assert_eq!(route.arrivals, vec![0, 18, 41, 54]);
+        assert_eq!(route.positions.len(), 55);
+        assert_eq!(route.positions[18], end);
+        assert!(route
+            .positions
+            .iter()
+            .enumerate()
+            .all(|(minute, pos)| world.is_free(pos, minute)));
+        let first_leg = world.trip(&[start, end]).unwrap();
+        let replay = world.replay(&first_leg);
+        let frames: Vec<&str> = replay.split("\n\n").collect();
+        assert_eq!(frames.len(), 19);
+        assert!(frames[18].starts_with("Minute 18, move down:"));
+        assert!(frames.iter().any(|f| f.contains(", wait:")));
+        assert_eq!(
+            frames[1],
+            "Minute 1, move down:\n#.######\n#E>3.<.#\n#<..<<.#\n#>2.22.#\n#>v..^<#\n######.#"
+        );
+    }
 }
@@ -0,0 +1,19 @@
+#![no_main]
+
+use std::str::FromStr;
+
+use libfuzzer_sys::fuzz_target;
+
+#[allow(dead_code)]
+#[path = "../../src/snafu.rs"]
+mod snafu;
+
+use snafu::Snafu;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(s) = std::str::from_utf8(data) {
+        if let Ok(n) = Snafu::from_str(s) {
+            assert_eq!(Snafu::from_str(&n.to_string()).unwrap(), n);
+        }
+    }
+});
@@ -0,0 +1,346 @@
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display};
+use std::iter::Sum;
+use std::marker::PhantomData;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+// Symbols of a balanced numeral system, from the most negative digit to the
+// most positive one. There must be an odd number of them, the one in the
+// middle standing for zero.
+pub trait Numerals {
+    const SYMBOLS: &'static [char];
+
+    fn base() -> i64 {
+        Self::SYMBOLS.len() as i64
+    }
+
+    fn max_digit() -> i64 {
+        Self::base() / 2
+    }
+}
+
+pub struct SnafuNumerals;
+
+impl Numerals for SnafuNumerals {
+    const SYMBOLS: &'static [char] = &['=', '-', '0', '1', '2'];
+}
+
+#[derive(Debug)]
+pub struct InvalidDigit {}
+
+// A number written in a balanced numeral system, with its digits stored from
+// the least significant one and without leading zeros, so that zero has no
+// digits at all.
+pub struct Balanced<N: Numerals> {
+    digits: Vec<i8>,
+    numerals: PhantomData<N>,
+}
+
+impl<N: Numerals> Clone for Balanced<N> {
+    fn clone(&self) -> Self {
+        Self {
+            digits: self.digits.clone(),
+            numerals: PhantomData,
+        }
+    }
+}
+
+impl<N: Numerals> PartialEq for Balanced<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.digits == other.digits
+    }
+}
+
+impl<N: Numerals> Eq for Balanced<N> {}
+
+impl<N: Numerals> Debug for Balanced<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+pub type Snafu = Balanced<SnafuNumerals>;
+
+impl<N: Numerals> Balanced<N> {
+    pub fn zero() -> Self {
+        Self {
+            digits: Vec::new(),
+            numerals: PhantomData,
+        }
+    }
+
+    // Builds a number from arbitrary digit values, carrying over to the next
+    // digit until every digit is in the balanced range.
+    pub fn from_raw_digits(raw: impl IntoIterator<Item = i64>) -> Self {
+        let base = N::base();
+        let mut digits = Vec::new();
+        let mut carry = 0;
+        let mut raw = raw.into_iter();
+        loop {
+            let value = match raw.next() {
+                Some(value) => value + carry,
+                None if carry != 0 => carry,
+                None => break,
+            };
+            let mut digit = value.rem_euclid(base);
+            if digit > N::max_digit() {
+                digit -= base;
+            }
+            digits.push(digit as i8);
+            carry = (value - digit) / base;
+        }
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Self {
+            digits,
+            numerals: PhantomData,
+        }
+    }
+
+    pub fn from_i128(value: i128) -> Self {
+        let base = N::base() as i128;
+        let mut digits = Vec::new();
+        let mut remains = value;
+        while remains != 0 {
+            let mut digit = remains.rem_euclid(base);
+            if digit > N::max_digit() as i128 {
+                digit -= base;
+            }
+            digits.push(digit as i8);
+            remains = (remains - digit) / base;
+        }
+        Self {
+            digits,
+            numerals: PhantomData,
+        }
+    }
+
+    pub fn signum(&self) -> i8 {
+        self.digits.last().map_or(0, |d| d.signum())
+    }
+
+    pub fn to_i128(&self) -> Option<i128> {
+        self.digits.iter().rev().try_fold(0_i128, |acc, d| {
+            acc.checked_mul(N::base() as i128)?.checked_add(*d as i128)
+        })
+    }
+
+    #[cfg(test)]
+    pub fn value(&self) -> i64 {
+        self.to_i128()
+            .and_then(|v| i64::try_from(v).ok())
+            .expect("Number does not fit in an i64")
+    }
+}
+
+impl<N: Numerals> FromStr for Balanced<N> {
+    type Err = InvalidDigit;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(InvalidDigit {});
+        }
+        let digits = s
+            .chars()
+            .rev()
+            .map(
+                |c| match N::SYMBOLS.iter().position(|symbol| *symbol == c) {
+                    Some(i) => Ok(i as i64 - N::max_digit()),
+                    None => Err(InvalidDigit {}),
+                },
+            )
+            .collect::<Result<Vec<i64>, InvalidDigit>>()?;
+        Ok(Self::from_raw_digits(digits))
+    }
+}
+
+impl<N: Numerals> From<i64> for Balanced<N> {
+    fn from(value: i64) -> Self {
+        Self::from_i128(value as i128)
+    }
+}
+
+impl<N: Numerals> Display for Balanced<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = |d: i8| N::SYMBOLS[(d as i64 + N::max_digit()) as usize];
+        if self.digits.is_empty() {
+            return write!(f, "{}", symbol(0));
+        }
+        self.digits
+            .iter()
+            .rev()
+            .try_for_each(|d| write!(f, "{}", symbol(*d)))
+    }
+}
+
+impl<N: Numerals> Neg for Balanced<N> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self {
+            digits: self.digits.iter().map(|d| -d).collect(),
+            numerals: PhantomData,
+        }
+    }
+}
+
+impl<N: Numerals> Add for Balanced<N> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let length = self.digits.len().max(rhs.digits.len());
+        Self::from_raw_digits((0..length).map(|i| {
+            *self.digits.get(i).unwrap_or(&0) as i64 + *rhs.digits.get(i).unwrap_or(&0) as i64
+        }))
+    }
+}
+
+impl<N: Numerals> Sub for Balanced<N> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl<N: Numerals> Mul for Balanced<N> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        if self.digits.is_empty() || rhs.digits.is_empty() {
+            return Self::zero();
+        }
+        let mut raw = vec![0_i64; self.digits.len() + rhs.digits.len()];
+        for (i, a) in self.digits.iter().enumerate() {
+            for (j, b) in rhs.digits.iter().enumerate() {
+                raw[i + j] += *a as i64 * *b as i64;
+            }
+        }
+        Self::from_raw_digits(raw)
+    }
+}
+
+impl<N: Numerals> PartialOrd for Balanced<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N: Numerals> Ord for Balanced<N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.clone() - other.clone()).signum().cmp(&0)
+    }
+}
+
+impl<N: Numerals> Sum for Balanced<N> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |acc, n| acc + n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use proptest::prelude::*;
+
+    use super::{Balanced, Numerals, Snafu};
+
+    struct Ternary;
+
+    impl Numerals for Ternary {
+        const SYMBOLS: &'static [char] = &['T', '0', '1'];
+    }
+
+    #[test]
+    fn test_parse_nb() {
+        assert_eq!(Snafu::from_str("1=").unwrap().value(), 3);
+        assert_eq!(Snafu::from_str("12").unwrap().value(), 7);
+        assert_eq!(Snafu::from_str("1=11-2").unwrap().value(), 2022);
+    }
+
+    #[test]
+    fn test_serialize_nb() {
+        assert_eq!(Snafu::from(3).to_string(), "1=");
+        assert_eq!(Snafu::from(7).to_string(), "12");
+        assert_eq!(Snafu::from(2022).to_string(), "1=11-2");
+        assert_eq!(Snafu::from(0).to_string(), "0");
+        assert_eq!(Snafu::from(-2022).to_string(), "-2--1=");
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = Snafu::from(2022);
+        let b = Snafu::from(-37);
+        assert_eq!((a.clone() + b.clone()).value(), 1985);
+        assert_eq!((a.clone() - b.clone()).value(), 2059);
+        assert_eq!((a.clone() * b.clone()).value(), -74814);
+        assert!(b < a);
+        let big = Snafu::from(i64::MAX) * Snafu::from(i64::MAX);
+        assert_eq!(big.to_i128(), Some(i64::MAX as i128 * i64::MAX as i128));
+        assert_eq!(
+            [a, b, big.clone()].into_iter().sum::<Snafu>(),
+            big + Snafu::from(1985)
+        );
+    }
+
+    #[test]
+    fn test_other_base() {
+        assert_eq!(Balanced::<Ternary>::from(8).to_string(), "10T");
+        assert_eq!(Balanced::<Ternary>::from_str("T1").unwrap().value(), -2);
+    }
+
+    #[test]
+    fn test_i64_extremes() {
+        for n in [0, 1, -1, 2, -2, 3, -3, i64::MAX, i64::MIN, i64::MIN + 1] {
+            assert_eq!(Snafu::from(n).value(), n);
+            assert_eq!(
+                Snafu::from_str(&Snafu::from(n).to_string())
+                    .unwrap()
+                    .value(),
+                n
+            );
+        }
+        let sum: Snafu = [Snafu::from(i64::MAX), Snafu::from(i64::MAX)]
+            .into_iter()
+            .sum();
+        assert_eq!(sum.to_i128(), Some(2 * i64::MAX as i128));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_value_round_trip(n in any::<i64>()) {
+            prop_assert_eq!(Snafu::from(n).value(), n);
+        }
+
+        #[test]
+        fn prop_string_round_trip(n in any::<i64>()) {
+            let written = Snafu::from(n).to_string();
+            let parsed = Snafu::from_str(&written).unwrap();
+            prop_assert_eq!(parsed.to_string(), written);
+            prop_assert_eq!(parsed.value(), n);
+        }
+
+        #[test]
+        fn prop_canonical_strings_round_trip(s in "[12\\-=][=\\-012]{0,40}") {
+            prop_assert_eq!(Snafu::from_str(&s).unwrap().to_string(), s);
+        }
+
+        #[test]
+        fn prop_arithmetic_matches_i128(a in any::<i64>(), b in any::<i64>()) {
+            let (x, y) = (Snafu::from(a), Snafu::from(b));
+            let (a, b) = (a as i128, b as i128);
+            prop_assert_eq!((x.clone() + y.clone()).to_i128(), Some(a + b));
+            prop_assert_eq!((x.clone() - y.clone()).to_i128(), Some(a - b));
+            prop_assert_eq!((x.clone() * y.clone()).to_i128(), Some(a * b));
+            prop_assert_eq!(x.cmp(&y), a.cmp(&b));
+        }
+
+        #[test]
+        fn prop_from_str_never_panics(s in "\\PC*") {
+            let _ = Snafu::from_str(&s);
+        }
+    }
+}
@@ -1,46 +1,272 @@
-use std::collections::HashSet;
+use std::env;
+use std::fmt::Display;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-fn priority(input: &char) -> i32 {
-    let char_value = *input as i32;
-    if char_value > 96 {
-        return char_value - 96;
+fn priority(input: char) -> Option<u32> {
+    match input {
+        'a'..='z' => Some(input as u32 - 96),
+        'A'..='Z' => Some(input as u32 - 38),
+        _ => None,
+    }
+}
+
+fn item(priority: u32) -> char {
+    match priority {
+        1..=26 => (priority + 96) as u8 as char,
+        _ => (priority + 38) as u8 as char,
+    }
+}
+
+// Items as a bitmask, bit n standing for the item of priority n.
+fn mask(items: &str) -> Result<u64, char> {
+    items
+        .chars()
+        .try_fold(0, |acc, c| priority(c).map(|p| acc | 1 << p).ok_or(c))
+}
+
+fn items_in(mask: u64) -> String {
+    (1..=52).filter(|p| mask & 1 << p != 0).map(item).collect()
+}
+
+fn mask_priority(mask: u64) -> u32 {
+    mask.trailing_zeros()
+}
+
+#[derive(Debug, PartialEq)]
+enum Diagnostic {
+    InvalidItem { line: usize, item: char },
+    OddLength { line: usize },
+    // Compartments of a rucksack sharing no item, or several.
+    Compartments { line: usize, shared: String },
+    // Group, identified by its first line, without exactly one badge.
+    Badge { line: usize, shared: String },
+    // Group, identified by its first line, holding a rucksack with an
+    // invalid item: it still counts towards the group but has no badge.
+    BrokenGroup { line: usize },
+    IncompleteGroup { line: usize, size: usize },
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Diagnostic::InvalidItem { line, item } => {
+                write!(f, "line {}: invalid item {:?}", line, item)
+            }
+            Diagnostic::OddLength { line } => {
+                write!(f, "line {}: odd number of items", line)
+            }
+            Diagnostic::Compartments { line, shared } => write!(
+                f,
+                "line {}: compartments share {:?} instead of a single item",
+                line, shared
+            ),
+            Diagnostic::Badge { line, shared } => write!(
+                f,
+                "group starting line {}: shares {:?} instead of a single badge",
+                line, shared
+            ),
+            Diagnostic::BrokenGroup { line } => write!(
+                f,
+                "group starting line {}: no badge, a rucksack has invalid items",
+                line
+            ),
+            Diagnostic::IncompleteGroup { line, size } => {
+                write!(f, "group starting line {}: only {} rucksacks", line, size)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Report {
+    compartments_priority: u32,
+    badges_priority: u32,
+    diagnostics: Vec<Diagnostic>,
+}
+
+struct Analyzer {
+    group_size: usize,
+    report: Report,
+    // Rucksacks of the current group, None for those with invalid items.
+    group: Vec<Option<u64>>,
+    group_line: usize,
+}
+
+impl Analyzer {
+    fn new(group_size: usize) -> Self {
+        Self {
+            group_size,
+            report: Report::default(),
+            group: Vec::new(),
+            group_line: 0,
+        }
+    }
+
+    fn add_rucksack(&mut self, line: usize, content: &str) {
+        let content = content.trim();
+        if content.is_empty() {
+            return;
+        }
+        let diagnostics = &mut self.report.diagnostics;
+        let whole = match mask(content) {
+            Ok(whole) => whole,
+            Err(item) => {
+                diagnostics.push(Diagnostic::InvalidItem { line, item });
+                self.join_group(line, None);
+                return;
+            }
+        };
+        let (left, right) = content.split_at(content.len() / 2);
+        let (left, right) = (mask(left).unwrap(), mask(right).unwrap());
+        if content.len() % 2 == 1 {
+            diagnostics.push(Diagnostic::OddLength { line });
+        } else if (left & right).count_ones() == 1 {
+            self.report.compartments_priority += mask_priority(left & right);
+        } else {
+            diagnostics.push(Diagnostic::Compartments {
+                line,
+                shared: items_in(left & right),
+            });
+        }
+        self.join_group(line, Some(whole));
+    }
+
+    fn join_group(&mut self, line: usize, rucksack: Option<u64>) {
+        if self.group.is_empty() {
+            self.group_line = line;
+        }
+        self.group.push(rucksack);
+        if self.group.len() < self.group_size {
+            return;
+        }
+        let diagnostics = &mut self.report.diagnostics;
+        let shared = self
+            .group
+            .drain(..)
+            .try_fold(u64::MAX, |acc, m| m.map(|m| acc & m));
+        match shared {
+            None => diagnostics.push(Diagnostic::BrokenGroup {
+                line: self.group_line,
+            }),
+            Some(shared) if shared.count_ones() == 1 => {
+                self.report.badges_priority += mask_priority(shared);
+            }
+            Some(shared) => diagnostics.push(Diagnostic::Badge {
+                line: self.group_line,
+                shared: items_in(shared),
+            }),
+        }
+    }
+
+    fn finish(mut self) -> Report {
+        if !self.group.is_empty() {
+            self.report.diagnostics.push(Diagnostic::IncompleteGroup {
+                line: self.group_line,
+                size: self.group.len(),
+            });
+        }
+        self.report
     }
-    return char_value - 38;
 }
 
 fn main() -> std::io::Result<()> {
+    let usage = "Usage: dec3 [--group-size <n>]";
+    let mut group_size = 3;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--group-size" => {
+                group_size = match args.next().expect(usage).parse() {
+                    Ok(n) if n > 0 => n,
+                    _ => panic!("{}", usage),
+                }
+            }
+            _ => panic!("{}", usage),
+        }
+    }
     let file = File::open("./src/input.txt")?;
-    let mut lines = BufReader::new(file).lines();
-    let mut total_priority = 0;
-    loop {
-        let line = lines.next();
-        let line_content = match line {
-            None => break,
-            Some(Err(_)) => break,
-            Some(Ok(content)) => content,
-        };
-        if &line_content == "" {
-            break;
-        };
-        let group = [
-            line_content,
-            lines.next().unwrap().unwrap(),
-            lines.next().unwrap().unwrap(),
-        ];
-        let letters = group.map(|g| g.chars().collect::<HashSet<char>>());
-        let letter_in_common = letters
-            .iter()
-            .fold(letters[0].clone(), |acc, set| {
-                acc.intersection(set).map(|v| *v).collect::<HashSet<char>>()
-            })
-            .iter()
-            .next()
-            .unwrap()
-            .clone();
-        total_priority += priority(&letter_in_common);
-    }
-    println!("The total priority of all rucksacks is {}", total_priority);
+    let lines = BufReader::new(file).lines();
+    let mut analyzer = Analyzer::new(group_size);
+    for (i, line) in lines.enumerate() {
+        analyzer.add_rucksack(i + 1, &line?);
+    }
+    let report = analyzer.finish();
+    for diagnostic in report.diagnostics.iter() {
+        println!("Warning, {}", diagnostic);
+    }
+    println!(
+        "The total priority of items in both compartments is {}",
+        report.compartments_priority
+    );
+    println!(
+        "The total priority of all rucksacks is {}",
+        report.badges_priority
+    );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{Analyzer, Diagnostic};
+
+    fn analyze(content: &str, group_size: usize) -> Analyzer {
+        let mut analyzer = Analyzer::new(group_size);
+        for (i, line) in content.lines().enumerate() {
+            analyzer.add_rucksack(i + 1, line);
+        }
+        analyzer
+    }
+
+    #[test]
+    fn test_example() {
+        let content = fs::read_to_string("./src/test.txt").unwrap();
+        let report = analyze(&content, 3).finish();
+        assert_eq!(report.compartments_priority, 157);
+        assert_eq!(report.badges_priority, 70);
+        assert!(report.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let report = analyze("abcA\n\nabca\nxy1z\nabc\naa\n", 2).finish();
+        assert_eq!(report.compartments_priority, 2);
+        assert_eq!(
+            report.diagnostics,
+            vec![
+                Diagnostic::Compartments {
+                    line: 1,
+                    shared: String::new()
+                },
+                Diagnostic::Badge {
+                    line: 1,
+                    shared: String::from("abc")
+                },
+                Diagnostic::InvalidItem { line: 4, item: '1' },
+                Diagnostic::OddLength { line: 5 },
+                Diagnostic::BrokenGroup { line: 4 },
+                Diagnostic::IncompleteGroup { line: 6, size: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_corrupt_line_in_group() {
+        let content = fs::read_to_string("./src/test.txt").unwrap();
+        let mut lines: Vec<&str> = content.lines().collect();
+        lines[1] = "jqHRNqRjqzjGD1LrsFMfFZSrLrFZsSL";
+        let report = analyze(&lines.join("\n"), 3).finish();
+        assert_eq!(report.compartments_priority, 157 - 38);
+        // The first group loses its badge, the second one keeps its own.
+        assert_eq!(report.badges_priority, 70 - 18);
+        assert_eq!(
+            report.diagnostics,
+            vec![
+                Diagnostic::InvalidItem { line: 2, item: '1' },
+                Diagnostic::BrokenGroup { line: 1 },
+            ]
+        );
+    }
+}
@@ -3,46 +3,179 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::str::FromStr;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 struct SectionRange {
-    start: i32,
-    end: i32,
+    start: i64,
+    end: i64,
 }
 
 impl SectionRange {
     fn full_overlap(&self, other: &Self) -> bool {
-        return (self.start >= other.start && self.end <= other.end)
-            || (self.start <= other.start && self.end >= other.end);
+        (self.start >= other.start && self.end <= other.end)
+            || (self.start <= other.start && self.end >= other.end)
     }
 
     fn overlap(&self, other: &Self) -> bool {
-        return self.start <= other.end && self.end >= other.start;
+        self.start <= other.end && self.end >= other.start
     }
 }
 
-fn cap_to_i32(cap: Option<Match>) -> i32 {
-    i32::from_str(cap.unwrap().as_str()).unwrap()
+// Number of elves covering each section, as consecutive runs of sections
+// sharing the same count, from the lowest to the highest assigned section.
+struct Coverage {
+    runs: Vec<(SectionRange, u32)>,
 }
 
-fn main() -> std::io::Result<()> {
-    let range_def = Regex::new(r"^(\d+)-(\d+),(\d+)-(\d+)$").unwrap();
-    let file = File::open("./src/input.txt")?;
-    let lines = BufReader::new(file).lines();
-    let mut overlapping = 0;
-    for line in lines {
-        let line_content = line.unwrap();
-        let captures = range_def.captures(&line_content).unwrap();
-        let first_elve_range = SectionRange {
-            start: cap_to_i32(captures.get(1)),
-            end: cap_to_i32(captures.get(2)),
-        };
-        let second_elve_range = SectionRange {
-            start: cap_to_i32(captures.get(3)),
-            end: cap_to_i32(captures.get(4)),
-        };
-        if first_elve_range.overlap(&second_elve_range) {
-            overlapping += 1;
+impl Coverage {
+    fn sweep(ranges: &[SectionRange]) -> Self {
+        let mut events: Vec<(i64, i32)> = ranges
+            .iter()
+            .flat_map(|r| [(r.start, 1), (r.end + 1, -1)])
+            .collect();
+        events.sort();
+        let mut runs = Vec::new();
+        let mut count: i32 = 0;
+        let mut events = events.into_iter().peekable();
+        while let Some((section, delta)) = events.next() {
+            count += delta;
+            match events.peek() {
+                Some((next, _)) if *next > section => runs.push((
+                    SectionRange {
+                        start: section,
+                        end: next - 1,
+                    },
+                    count as u32,
+                )),
+                _ => {}
+            }
         }
+        Self { runs }
+    }
+
+    fn uncovered(&self) -> Vec<SectionRange> {
+        self.runs
+            .iter()
+            .filter(|(_, count)| *count == 0)
+            .map(|(range, _)| *range)
+            .collect()
+    }
+
+    fn min_count(&self, range: &SectionRange) -> u32 {
+        let first = self.runs.partition_point(|(run, _)| run.end < range.start);
+        self.runs[first..]
+            .iter()
+            .take_while(|(run, _)| run.start <= range.end)
+            .map(|(_, count)| *count)
+            .min()
+            .unwrap_or(0)
     }
+
+    // Elves, by index, whose every section is also covered by someone else.
+    // Each of them could be dropped on its own, not necessarily all together.
+    fn redundant(&self, ranges: &[SectionRange]) -> Vec<usize> {
+        ranges
+            .iter()
+            .enumerate()
+            .filter(|(_, range)| self.min_count(range) >= 2)
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+fn cap_to_i64(cap: Option<Match>) -> i64 {
+    i64::from_str(cap.unwrap().as_str()).unwrap()
+}
+
+fn parse_pairs(lines: impl Iterator<Item = String>) -> Vec<(SectionRange, SectionRange)> {
+    let range_def = Regex::new(r"^(\d+)-(\d+),(\d+)-(\d+)$").unwrap();
+    lines
+        .filter(|l| !l.is_empty())
+        .map(|line_content| {
+            let captures = range_def.captures(&line_content).unwrap();
+            let first_elve_range = SectionRange {
+                start: cap_to_i64(captures.get(1)),
+                end: cap_to_i64(captures.get(2)),
+            };
+            let second_elve_range = SectionRange {
+                start: cap_to_i64(captures.get(3)),
+                end: cap_to_i64(captures.get(4)),
+            };
+            (first_elve_range, second_elve_range)
+        })
+        .collect()
+}
+
+fn main() -> std::io::Result<()> {
+    let file = File::open("./src/input.txt")?;
+    let lines = BufReader::new(file).lines().map(|l| l.unwrap());
+    let pairs = parse_pairs(lines);
+    let fully_overlapping = pairs.iter().filter(|(a, b)| a.full_overlap(b)).count();
+    let overlapping = pairs.iter().filter(|(a, b)| a.overlap(b)).count();
+    println!("There are {fully_overlapping} assignments where one range contains the other");
     println!("There are {overlapping} assignments that are overlapping");
+    let ranges: Vec<SectionRange> = pairs.iter().flat_map(|(a, b)| [*a, *b]).collect();
+    let coverage = Coverage::sweep(&ranges);
+    let uncovered: Vec<String> = coverage
+        .uncovered()
+        .iter()
+        .map(|r| format!("{}-{}", r.start, r.end))
+        .collect();
+    if uncovered.is_empty() {
+        println!("Every section is covered by at least one elf");
+    } else {
+        println!("Sections nobody covers: {}", uncovered.join(", "));
+    }
+    let redundant = coverage.redundant(&ranges);
+    println!(
+        "{} elves out of {} only cover sections someone else covers: {}",
+        redundant.len(),
+        ranges.len(),
+        redundant
+            .iter()
+            .map(|i| (i + 1).to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{parse_pairs, Coverage, SectionRange};
+
+    #[test]
+    fn test_example() {
+        let content = fs::read_to_string("./src/test.txt").unwrap();
+        let pairs = parse_pairs(content.lines().map(String::from));
+        assert_eq!(pairs.iter().filter(|(a, b)| a.full_overlap(b)).count(), 2);
+        assert_eq!(pairs.iter().filter(|(a, b)| a.overlap(b)).count(), 4);
+        let ranges: Vec<SectionRange> = pairs.iter().flat_map(|(a, b)| [*a, *b]).collect();
+        let coverage = Coverage::sweep(&ranges);
+        let counts: Vec<u32> = (2..=9)
+            .map(|s| coverage.min_count(&SectionRange { start: s, end: s }))
+            .collect();
+        assert_eq!(counts, vec![4, 5, 7, 7, 8, 6, 4, 1]);
+        assert!(coverage.uncovered().is_empty());
+        assert_eq!(
+            coverage.redundant(&ranges),
+            vec![0, 1, 2, 3, 4, 6, 7, 8, 9, 10, 11]
+        );
+    }
+
+    #[test]
+    fn test_gaps() {
+        let pairs = parse_pairs(["1-2,6-7", "2-2,10-10"].into_iter().map(String::from));
+        let ranges: Vec<SectionRange> = pairs.iter().flat_map(|(a, b)| [*a, *b]).collect();
+        let coverage = Coverage::sweep(&ranges);
+        assert_eq!(
+            coverage.uncovered(),
+            vec![
+                SectionRange { start: 3, end: 5 },
+                SectionRange { start: 8, end: 9 }
+            ]
+        );
+        assert_eq!(coverage.redundant(&ranges), vec![2]);
+    }
+}
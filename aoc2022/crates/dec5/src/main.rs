@@ -1,108 +1,266 @@
 use regex::{Match, Regex};
-use std::cell::RefCell;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::env;
+use std::fmt::Display;
+use std::fs;
 use std::str::FromStr;
 
-struct Stack<'a> {
-    crates: RefCell<Vec<&'a char>>,
+struct Stack {
+    crates: Vec<char>,
 }
 
-impl<'a> Stack<'a> {
+impl Stack {
     fn new() -> Self {
-        Self {
-            crates: RefCell::new(Vec::new()),
-        }
+        Self { crates: Vec::new() }
     }
-    fn pop(&self) -> Option<&'a char> {
-        self.crates.borrow_mut().pop()
+    fn push(&mut self, value: char) {
+        self.crates.push(value)
     }
-    fn push(&self, value: &'a char) {
-        self.crates.borrow_mut().push(value)
+    fn top(&self) -> Option<char> {
+        self.crates.last().copied()
     }
-    fn top(&self) -> Option<&'a char> {
-        self.crates.borrow().last().and_then(|c| Some(*c))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Crane {
+    // Moves crates one at a time, reversing their order.
+    CrateMover9000,
+    // Moves crates as a block, keeping their order.
+    CrateMover9001,
+}
+
+impl FromStr for Crane {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "9000" => Ok(Crane::CrateMover9000),
+            "9001" => Ok(Crane::CrateMover9001),
+            _ => Err(ParseError {
+                line: 0,
+                content: s.to_string(),
+            }),
+        }
     }
 }
 
-struct Ship<'a> {
-    stacks: [Stack<'a>; 9],
+#[derive(Debug, PartialEq)]
+enum MoveError {
+    NoSuchStack(usize),
+    NotEnoughCrates {
+        stack: usize,
+        available: usize,
+        requested: usize,
+    },
 }
 
-impl<'a> Ship<'a> {
-    fn new() -> Self {
+impl Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveError::NoSuchStack(stack) => write!(f, "there is no stack {}", stack),
+            MoveError::NotEnoughCrates {
+                stack,
+                available,
+                requested,
+            } => write!(
+                f,
+                "stack {} only holds {} crates, {} requested",
+                stack, available, requested
+            ),
+        }
+    }
+}
+
+struct Ship {
+    stacks: Vec<Stack>,
+}
+
+impl Ship {
+    fn new(count: usize) -> Self {
         Self {
-            stacks: [(); 9].map(|_| Stack::new()),
+            stacks: (0..count).map(|_| Stack::new()).collect(),
         }
     }
 
-    fn move_crates(&self, from: usize, to: usize, quantity: usize) {
-        let from_stack = self.stacks.get(from - 1).unwrap();
-        let to_stack = self.stacks.get(to - 1).unwrap();
-        let mut from_crates = from_stack.crates.borrow_mut();
+    fn move_crates(
+        &mut self,
+        from: usize,
+        to: usize,
+        quantity: usize,
+        crane: Crane,
+    ) -> Result<(), MoveError> {
+        for stack in [from, to] {
+            if stack == 0 || stack > self.stacks.len() {
+                return Err(MoveError::NoSuchStack(stack));
+            }
+        }
+        let from_crates = &mut self.stacks[from - 1].crates;
         let length = from_crates.len();
-        to_stack
-            .crates
-            .borrow_mut()
-            .extend(from_crates.drain(length - quantity..length))
+        if quantity > length {
+            return Err(MoveError::NotEnoughCrates {
+                stack: from,
+                available: length,
+                requested: quantity,
+            });
+        }
+        let mut moved: Vec<char> = from_crates.drain(length - quantity..length).collect();
+        if crane == Crane::CrateMover9000 {
+            moved.reverse();
+        }
+        self.stacks[to - 1].crates.extend(moved);
+        Ok(())
+    }
+
+    fn tops(&self) -> String {
+        self.stacks.iter().filter_map(|s| s.top()).collect()
     }
 }
 
 #[derive(Debug)]
-struct ParseError {}
-
-fn parse_initial_layout(input: &str) -> Result<[Option<char>; 9], ParseError> {
-    let mut chars = input.chars();
-    chars.next();
-    return chars
-        .step_by(4)
-        .take(9)
-        .map(|c| match c {
-            ' ' => None,
-            letter => Some(letter),
-        })
-        .collect::<Vec<Option<char>>>()
-        .try_into()
-        .or(Err(ParseError {}));
+struct ParseError {
+    line: usize,
+    content: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.content)
+    }
 }
 
-fn cap_to_usize(cap: Option<Match>) -> usize {
-    usize::from_str(cap.unwrap().as_str()).unwrap()
+// Builds the ship from the drawing, the numbered footer line giving the
+// number of stacks.
+fn parse_initial_layout(drawing: &[&str]) -> Result<Ship, ParseError> {
+    let (footer, layers) = drawing.split_last().ok_or(ParseError {
+        line: 1,
+        content: String::from("missing stack drawing"),
+    })?;
+    let mut count = 0;
+    for label in footer.split_whitespace() {
+        match usize::from_str(label) {
+            Ok(n) if n == count + 1 => count = n,
+            _ => {
+                return Err(ParseError {
+                    line: drawing.len(),
+                    content: footer.to_string(),
+                })
+            }
+        }
+    }
+    let columns: Vec<usize> = (0..count).map(|i| 1 + 4 * i).collect();
+    let mut ship = Ship::new(columns.len());
+    for (i, layer) in layers.iter().enumerate().rev() {
+        for (stack, column) in columns.iter().enumerate() {
+            match layer.as_bytes().get(*column) {
+                None | Some(b' ') => {}
+                Some(c) if c.is_ascii_alphabetic() => ship.stacks[stack].push(*c as char),
+                Some(_) => {
+                    return Err(ParseError {
+                        line: i + 1,
+                        content: layer.to_string(),
+                    })
+                }
+            }
+        }
+    }
+    Ok(ship)
 }
 
-fn main() -> std::io::Result<()> {
-    let file = File::open("./src/input.txt")?;
-    let mut lines = BufReader::new(file).lines();
-    let ship = Ship::new();
-    let move_regex = Regex::new(r"^move (\d+) from (\d) to (\d)$").unwrap();
-    let mut initial_layout: Vec<[Option<char>; 9]> = Vec::new();
-    loop {
-        let content = lines.next().unwrap().unwrap();
-        match parse_initial_layout(&content) {
-            Ok(chars) => initial_layout.push(chars),
-            Err(_) => break,
+fn cap_to_usize(cap: Option<Match>) -> Option<usize> {
+    usize::from_str(cap?.as_str()).ok()
+}
+
+// Runs the procedure with the given crane, returning the crates on top and
+// the moves that could not be performed.
+fn rearrange(input: &str, crane: Crane) -> Result<(String, Vec<(usize, MoveError)>), ParseError> {
+    let lines: Vec<&str> = input.lines().collect();
+    let split = lines
+        .iter()
+        .position(|l| l.trim().is_empty())
+        .unwrap_or(lines.len());
+    let mut ship = parse_initial_layout(&lines[..split])?;
+    let move_regex = Regex::new(r"^move (\d+) from (\d+) to (\d+)$").unwrap();
+    let mut errors = Vec::new();
+    for (i, content) in lines.iter().enumerate().skip(split) {
+        if content.trim().is_empty() {
+            continue;
+        }
+        let error = || ParseError {
+            line: i + 1,
+            content: content.to_string(),
+        };
+        let capt = move_regex.captures(content.trim()).ok_or_else(error)?;
+        // Numbers too large for a usize cannot be read either.
+        let move_quantity = cap_to_usize(capt.get(1)).ok_or_else(error)?;
+        let move_from = cap_to_usize(capt.get(2)).ok_or_else(error)?;
+        let move_to = cap_to_usize(capt.get(3)).ok_or_else(error)?;
+        if let Err(e) = ship.move_crates(move_from, move_to, move_quantity, crane) {
+            errors.push((i + 1, e));
         }
     }
-    initial_layout.iter().rev().for_each(|line| {
-        line.iter()
-            .zip(ship.stacks.iter())
-            .for_each(|(char, stack)| match char {
-                Some(c) => stack.push(c),
-                _ => {}
-            })
-    });
-    for line in lines {
-        let content = line.unwrap();
-        if content.len() < 4 {
-            break;
+    Ok((ship.tops(), errors))
+}
+
+fn main() {
+    let usage = "Usage: dec5 [--crane 9000|9001]";
+    let mut cranes = vec![Crane::CrateMover9000, Crane::CrateMover9001];
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--crane" => cranes = vec![args.next().expect(usage).parse().expect(usage)],
+            _ => panic!("{}", usage),
         }
-        let capt = move_regex.captures(&content).unwrap();
-        let move_quantity = cap_to_usize(capt.get(1));
-        let move_from = cap_to_usize(capt.get(2));
-        let move_to = cap_to_usize(capt.get(3));
-        ship.move_crates(move_from, move_to, move_quantity);
-    }
-    let crates_at_top: String = ship.stacks.map(|s| s.top().unwrap()).into_iter().collect();
-    println!("The boxes are the top are {}", crates_at_top);
-    Ok(())
+    }
+    let input = fs::read_to_string("./src/input.txt").unwrap();
+    for crane in cranes {
+        match rearrange(&input, crane) {
+            Ok((tops, errors)) => {
+                for (line, error) in errors {
+                    println!("Skipped move on line {}: {}", line, error);
+                }
+                println!("With the {:?} the boxes at the top are {}", crane, tops);
+            }
+            Err(e) => println!("Invalid input at {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{rearrange, Crane, MoveError};
+
+    #[test]
+    fn test_example() {
+        let input = fs::read_to_string("./src/test.txt").unwrap();
+        let (tops, errors) = rearrange(&input, Crane::CrateMover9000).unwrap();
+        assert_eq!(tops, "CMZ");
+        assert!(errors.is_empty());
+        let (tops, _) = rearrange(&input, Crane::CrateMover9001).unwrap();
+        assert_eq!(tops, "MCD");
+    }
+
+    #[test]
+    fn test_invalid_moves() {
+        let input = "[A]         [B]\n 1   2   3   4 \n\nmove 2 from 1 to 2\nmove 1 from 5 to 1\nmove 1 from 4 to 2\n";
+        let (tops, errors) = rearrange(input, Crane::CrateMover9000).unwrap();
+        assert_eq!(tops, "AB");
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    4,
+                    MoveError::NotEnoughCrates {
+                        stack: 1,
+                        available: 1,
+                        requested: 2
+                    }
+                ),
+                (5, MoveError::NoSuchStack(5)),
+            ]
+        );
+        let overflowing = format!("{}move 1 from 99999999999999999999999 to 1\n", input);
+        let error = rearrange(&overflowing, Crane::CrateMover9000).unwrap_err();
+        assert_eq!(error.line, 7);
+    }
 }
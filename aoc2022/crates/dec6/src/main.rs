@@ -1,28 +1,117 @@
-use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::env;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufReader, Read};
+
+#[derive(Debug, PartialEq)]
+struct Marker {
+    size: usize,
+    // Position of the last character of the marker, counting from 1.
+    end: usize,
+    content: String,
+}
+
+// Finds, for each window size, the first run of that many distinct bytes.
+// All sizes share the table of the last position each byte was seen at: a
+// window starts right after the latest repeat of any byte it contains. Line
+// breaks are never part of a marker.
+fn find_markers<R: Read>(reader: R, sizes: &[usize]) -> std::io::Result<Vec<Option<Marker>>> {
+    let longest = sizes.iter().copied().max().unwrap_or(0);
+    let mut last_seen: [Option<usize>; 256] = [None; 256];
+    let mut starts = vec![0; sizes.len()];
+    let mut markers: Vec<Option<Marker>> = sizes.iter().map(|_| None).collect();
+    let mut recent: VecDeque<u8> = VecDeque::with_capacity(longest);
+    let mut remaining = sizes.len();
+    for (i, byte) in BufReader::new(reader).bytes().enumerate() {
+        if remaining == 0 {
+            break;
+        }
+        let byte = byte?;
+        if recent.len() == longest {
+            recent.pop_front();
+        }
+        recent.push_back(byte);
+        let window_start = match byte {
+            b'\n' | b'\r' => i + 1,
+            _ => last_seen[byte as usize].map_or(0, |p| p + 1),
+        };
+        last_seen[byte as usize] = Some(i);
+        for (k, size) in sizes.iter().enumerate() {
+            starts[k] = starts[k].max(window_start);
+            if markers[k].is_none() && i + 1 - starts[k] >= *size {
+                let content: Vec<u8> = recent.iter().skip(recent.len() - size).copied().collect();
+                markers[k] = Some(Marker {
+                    size: *size,
+                    end: i + 1,
+                    content: String::from_utf8_lossy(&content).into_owned(),
+                });
+                remaining -= 1;
+            }
+        }
+    }
+    Ok(markers)
+}
 
 fn main() -> std::io::Result<()> {
+    let usage = "Usage: dec6 [--size <n>]...";
+    let mut sizes = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--size" => sizes.push(args.next().expect(usage).parse().expect(usage)),
+            _ => panic!("{}", usage),
+        }
+    }
+    if sizes.is_empty() {
+        sizes = vec![4, 14];
+    }
     let file = File::open("./src/input.txt")?;
-    let reader = BufReader::new(file);
-    let content = reader.lines().next().unwrap().unwrap();
-    let mut start_buffer: [char; 14] = ['z'].repeat(14).try_into().unwrap();
-    for (i, c) in content.char_indices() {
-        start_buffer = start_buffer[1..]
-            .into_iter()
-            .map(|v| *v)
-            .chain([c])
-            .collect::<Vec<char>>()
-            .try_into()
-            .unwrap();
-        if HashSet::<char>::from_iter(start_buffer).len() == 14 {
-            println!(
-                "The first 14 characters are {} and can be found finishing at position {}",
-                start_buffer.into_iter().collect::<String>(),
-                i + 1
-            );
-            break;
+    for (size, marker) in sizes.iter().zip(find_markers(file, &sizes)?) {
+        match marker {
+            Some(marker) => println!(
+                "The first {} characters are {} and can be found finishing at position {}",
+                marker.size, marker.content, marker.end
+            ),
+            None => println!("There is no marker of {} distinct characters", size),
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::find_markers;
+
+    fn ends(input: &str, sizes: &[usize]) -> Vec<Option<usize>> {
+        find_markers(input.as_bytes(), sizes)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.map(|m| m.end))
+            .collect()
+    }
+
+    #[test]
+    fn test_examples() {
+        let examples = [
+            ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19),
+            ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+            ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+            ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29),
+            ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26),
+        ];
+        for (input, packet, message) in examples {
+            assert_eq!(ends(input, &[4, 14]), vec![Some(packet), Some(message)]);
+        }
+        let markers = find_markers("mjqjpqmgbljsphdztnvjfqwrcgsmlb".as_bytes(), &[4]).unwrap();
+        assert_eq!(markers[0].as_ref().unwrap().content, "jpqm");
+    }
+
+    #[test]
+    fn test_other_sizes() {
+        assert_eq!(
+            ends("aab\nbcdd", &[1, 2, 3, 4]),
+            vec![Some(1), Some(3), Some(7), None]
+        );
+        assert_eq!(ends("", &[0, 1]), vec![None, None]);
+    }
+}
@@ -1,48 +1,43 @@
 use regex::Regex;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::str::FromStr;
 
-struct FileInfo {
-    size: i32,
-    name: String,
-}
-
 struct Folder {
     parent: Option<usize>,
     name: String,
+    folders: HashMap<String, usize>,
+    files: HashMap<String, u64>,
+    // Size of everything below this folder, cleared whenever it changes.
+    total_size: OnceCell<u64>,
 }
 
-struct FolderLink {
-    parent: usize,
-    child: usize,
-}
-
-struct FileLink {
-    parent: usize,
-    child: usize,
+impl Folder {
+    fn new(name: String, parent: Option<usize>) -> Self {
+        Self {
+            parent,
+            name,
+            folders: HashMap::new(),
+            files: HashMap::new(),
+            total_size: OnceCell::new(),
+        }
+    }
 }
 
-struct PromptState {
+struct FileSystem {
     current_position: usize,
     folders: Vec<Folder>,
-    files: Vec<FileInfo>,
-    folder_links: Vec<FolderLink>,
-    file_links: Vec<FileLink>,
 }
 
-impl PromptState {
+impl FileSystem {
     fn new() -> Self {
-        let root = Folder {
-            name: String::from("/"),
-            parent: None,
-        };
         Self {
             current_position: 0,
-            folders: Vec::from([root]),
-            files: Vec::new(),
-            folder_links: Vec::new(),
-            file_links: Vec::new(),
+            folders: Vec::from([Folder::new(String::from("/"), None)]),
         }
     }
 
@@ -51,118 +46,322 @@ impl PromptState {
     }
 
     fn move_to_parent(&mut self) {
-        self.current_position = self
-            .folder_links
-            .iter()
-            .find(|l| l.child == self.current_position)
-            .unwrap()
-            .parent
+        if let Some(parent) = self.folders[self.current_position].parent {
+            self.current_position = parent;
+        }
+    }
+
+    // Follows a `cd` argument, creating the folders that were never listed.
+    fn change_dir(&mut self, path: &str) {
+        if path.starts_with('/') {
+            self.move_to_root();
+        }
+        for part in path.split('/') {
+            match part {
+                "" | "." => {}
+                ".." => self.move_to_parent(),
+                name => self.move_to_dir(name),
+            }
+        }
     }
 
     fn move_to_dir(&mut self, name: &str) {
-        self.current_position = self
-            .folder_links
-            .iter()
-            .find(|l| l.parent == self.current_position && self.folders[l.child].name == name)
-            .unwrap()
-            .child
+        self.register_dir(String::from(name));
+        self.current_position = self.folders[self.current_position].folders[name];
     }
 
     fn register_dir(&mut self, name: String) {
+        if self.folders[self.current_position]
+            .folders
+            .contains_key(&name)
+        {
+            return;
+        }
         let id = self.folders.len();
-        let folder = Folder {
-            name,
-            parent: Some(self.current_position),
-        };
-        self.folders.push(folder);
-        self.folder_links.push(FolderLink {
-            parent: self.current_position,
-            child: id,
-        });
-    }
-
-    fn register_file(&mut self, name: String, size: i32) {
-        let id = self.files.len();
-        let file = FileInfo { name, size };
-        self.files.push(file);
-        self.file_links.push(FileLink {
-            parent: self.current_position,
-            child: id,
-        });
-    }
-
-    fn dir_size(&self, folder: usize) -> i32 {
-        let direct_files_size: i32 = self
-            .file_links
-            .iter()
-            .filter(|l| l.parent == folder)
-            .map(|l| self.files[l.child].size)
-            .sum();
-        let subfolder_size: i32 = self
-            .folder_links
-            .iter()
-            .filter(|l| l.parent == folder)
-            .map(|l| self.dir_size(l.child))
-            .sum();
-        return direct_files_size + subfolder_size;
+        self.folders
+            .push(Folder::new(name.clone(), Some(self.current_position)));
+        self.folders[self.current_position].folders.insert(name, id);
     }
-}
 
-fn main() -> std::io::Result<()> {
-    let file = File::open("./src/input.txt")?;
-    let lines = BufReader::new(file).lines();
-    let mut state = PromptState::new();
-    let cd_regex = Regex::new(r"^\$ cd ([a-zA-Z/\.]+)$").unwrap();
-    let ls_regex = Regex::new(r"^\$ ls$").unwrap();
-    let ls_output_regex = Regex::new(r"(dir|\d+) ([a-zA-Z\.]+)").unwrap();
-    for line in lines {
-        let content = line.unwrap();
-        match cd_regex.captures(&content) {
-            Some(capt) => {
-                let dest = capt.get(1).unwrap();
-                match dest.as_str() {
-                    "/" => state.move_to_root(),
-                    ".." => state.move_to_parent(),
-                    dest => state.move_to_dir(dest),
+    fn register_file(&mut self, name: String, size: u64) {
+        self.folders[self.current_position].files.insert(name, size);
+        let mut folder = Some(self.current_position);
+        while let Some(id) = folder {
+            self.folders[id].total_size.take();
+            folder = self.folders[id].parent;
+        }
+    }
+
+    fn dir_size(&self, folder: usize) -> u64 {
+        let folder = &self.folders[folder];
+        *folder.total_size.get_or_init(|| {
+            folder.files.values().sum::<u64>()
+                + folder
+                    .folders
+                    .values()
+                    .map(|f| self.dir_size(*f))
+                    .sum::<u64>()
+        })
+    }
+
+    fn lookup(&self, path: &str) -> Option<usize> {
+        path.split('/')
+            .filter(|part| !part.is_empty())
+            .try_fold(0, |folder, name| {
+                self.folders[folder].folders.get(name).copied()
+            })
+    }
+
+    fn path(&self, folder: usize) -> String {
+        match self.folders[folder].parent {
+            None => String::from("/"),
+            Some(0) => format!("/{}", self.folders[folder].name),
+            Some(parent) => format!("{}/{}", self.path(parent), self.folders[folder].name),
+        }
+    }
+
+    // Listing of the whole filesystem, in the style of the `tree` command.
+    fn tree(&self) -> String {
+        let mut output = String::new();
+        self.write_tree(0, 0, &mut output);
+        output
+    }
+
+    fn write_tree(&self, folder: usize, depth: usize, output: &mut String) {
+        let indent = "  ".repeat(depth);
+        output.push_str(&format!(
+            "{}- {} (dir, size={})\n",
+            indent,
+            self.folders[folder].name,
+            self.dir_size(folder)
+        ));
+        let mut entries: Vec<(&String, Option<u64>, Option<usize>)> = self.folders[folder]
+            .folders
+            .iter()
+            .map(|(name, id)| (name, None, Some(*id)))
+            .chain(
+                self.folders[folder]
+                    .files
+                    .iter()
+                    .map(|(name, size)| (name, Some(*size), None)),
+            )
+            .collect();
+        entries.sort();
+        for (name, size, child) in entries {
+            match (size, child) {
+                (_, Some(child)) => self.write_tree(child, depth + 1, output),
+                (Some(size), None) => {
+                    output.push_str(&format!("{}  - {} (file, size={})\n", indent, name, size))
                 }
-                continue;
+                (None, None) => {}
             }
-            None => {}
         }
-        match ls_regex.captures(&content) {
-            Some(_) => continue,
-            None => {}
+    }
+
+    fn dirs_with_size_at_most(&self, limit: u64) -> Vec<usize> {
+        (0..self.folders.len())
+            .filter(|f| self.dir_size(*f) <= limit)
+            .collect()
+    }
+
+    // Smallest folder to delete for `needed` bytes to be free on a disk of
+    // `disk_size` bytes.
+    fn smallest_dir_freeing(&self, disk_size: u64, needed: u64) -> Option<usize> {
+        let available = disk_size.checked_sub(self.dir_size(0))?;
+        let to_free = needed.saturating_sub(available);
+        (0..self.folders.len())
+            .filter(|f| self.dir_size(*f) >= to_free)
+            .min_by_key(|f| self.dir_size(*f))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum LineError {
+    UnknownCommand,
+    OutputOutsideListing,
+    Malformed,
+}
+
+#[derive(Debug)]
+struct TranscriptError {
+    line: usize,
+    content: String,
+    kind: LineError,
+}
+
+impl Display for TranscriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.kind {
+            LineError::UnknownCommand => "unknown command",
+            LineError::OutputOutsideListing => "output line outside of a listing",
+            LineError::Malformed => "malformed line",
+        };
+        write!(f, "line {} ({}): {}", self.line, reason, self.content)
+    }
+}
+
+// Rebuilds the filesystem from a terminal transcript, along with the lines
+// that could not be understood.
+fn parse_transcript(lines: impl Iterator<Item = String>) -> (FileSystem, Vec<TranscriptError>) {
+    let cd_regex = Regex::new(r"^\$ cd (.+)$").unwrap();
+    let ls_regex = Regex::new(r"^\$ ls$").unwrap();
+    let command_regex = Regex::new(r"^\$").unwrap();
+    let dir_regex = Regex::new(r"^dir (.+)$").unwrap();
+    let file_regex = Regex::new(r"^(\d+) (.+)$").unwrap();
+    let mut state = FileSystem::new();
+    let mut errors = Vec::new();
+    let mut listing = false;
+    for (i, content) in lines.enumerate() {
+        let content = content.trim_end();
+        if content.is_empty() {
+            continue;
         }
-        match ls_output_regex.captures(&content) {
-            Some(capt) => {
-                let first_part = capt.get(1).unwrap().as_str();
-                let second_part = capt.get(2).unwrap().as_str();
-                match (first_part, second_part) {
-                    ("dir", name) => {
-                        state.register_dir(String::from(name));
-                    }
-                    (size, name) => {
-                        state.register_file(String::from(name), i32::from_str(size).unwrap())
-                    }
+        let mut error = |kind| {
+            errors.push(TranscriptError {
+                line: i + 1,
+                content: content.to_string(),
+                kind,
+            })
+        };
+        if command_regex.is_match(content) {
+            listing = ls_regex.is_match(content);
+            if let Some(capt) = cd_regex.captures(content) {
+                state.change_dir(capt.get(1).unwrap().as_str());
+            } else if !listing {
+                error(LineError::UnknownCommand);
+            }
+        } else if let Some(capt) = dir_regex.captures(content) {
+            if listing {
+                state.register_dir(String::from(capt.get(1).unwrap().as_str()));
+            } else {
+                error(LineError::OutputOutsideListing);
+            }
+        } else if let Some(capt) = file_regex.captures(content) {
+            match (listing, u64::from_str(capt.get(1).unwrap().as_str())) {
+                (true, Ok(size)) => {
+                    state.register_file(String::from(capt.get(2).unwrap().as_str()), size)
                 }
-                continue;
+                (false, _) => error(LineError::OutputOutsideListing),
+                (_, Err(_)) => error(LineError::Malformed),
             }
-            None => {}
+        } else {
+            error(LineError::Malformed);
+        }
+    }
+    (state, errors)
+}
+
+fn main() -> std::io::Result<()> {
+    let usage =
+        "Usage: dec7 [--disk <bytes>] [--needed <bytes>] [--limit <bytes>] [--size-of <path>]... [--tree]";
+    let mut disk_size = 70000000;
+    let mut needed = 30000000;
+    let mut limit = 100000;
+    let mut paths = Vec::new();
+    let mut show_tree = false;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tree" => show_tree = true,
+            "--disk" => disk_size = args.next().expect(usage).parse().expect(usage),
+            "--needed" => needed = args.next().expect(usage).parse().expect(usage),
+            "--limit" => limit = args.next().expect(usage).parse().expect(usage),
+            "--size-of" => paths.push(args.next().expect(usage)),
+            _ => panic!("{}", usage),
         }
     }
-    let available_space = 70000000 - state.dir_size(0);
-    let minimum_to_delete = 30000000 - available_space;
-    let minimum_folder_to_delete = state
-        .folders
-        .iter()
-        .enumerate()
-        .map(|(i, _)| state.dir_size(i))
-        .filter(|s| *s > minimum_to_delete)
-        .min()
-        .unwrap();
+    let file = File::open("./src/input.txt")?;
+    let lines = BufReader::new(file).lines().map(|l| l.unwrap());
+    let (state, errors) = parse_transcript(lines);
+    for error in errors.iter() {
+        println!("Skipped {}", error);
+    }
+    if show_tree {
+        print!("{}", state.tree());
+    }
+    let small_dirs_size: u64 = state
+        .dirs_with_size_at_most(limit)
+        .into_iter()
+        .map(|f| state.dir_size(f))
+        .sum();
     println!(
-        "The smallest directory we cna delete has a size of {}",
-        minimum_folder_to_delete
+        "Directories of at most {} bytes add up to {}",
+        limit, small_dirs_size
     );
+    match state.smallest_dir_freeing(disk_size, needed) {
+        Some(folder) => println!(
+            "The smallest directory we can delete is {} with a size of {}",
+            state.path(folder),
+            state.dir_size(folder)
+        ),
+        None => println!("No directory frees enough space"),
+    }
+    for path in paths {
+        match state.lookup(&path) {
+            Some(folder) => println!("{} has a size of {}", path, state.dir_size(folder)),
+            None => println!("{} does not exist", path),
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{parse_transcript, LineError};
+
+    #[test]
+    fn test_example() {
+        let content = fs::read_to_string("./src/test.txt").unwrap();
+        let (state, errors) = parse_transcript(content.lines().map(String::from));
+        assert!(errors.is_empty());
+        let small: u64 = state
+            .dirs_with_size_at_most(100000)
+            .into_iter()
+            .map(|f| state.dir_size(f))
+            .sum();
+        assert_eq!(small, 95437);
+        let folder = state.smallest_dir_freeing(70000000, 30000000).unwrap();
+        assert_eq!(state.path(folder), "/d");
+        assert_eq!(state.dir_size(folder), 24933642);
+        assert_eq!(state.lookup("/a/e").map(|f| state.dir_size(f)), Some(584));
+        assert!(state
+            .tree()
+            .starts_with("- / (dir, size=48381165)\n  - a (dir, size=94853)\n"));
+    }
+
+    #[test]
+    fn test_unusual_transcript() {
+        let transcript = [
+            "$ cd /",
+            "$ ls",
+            "12 file_1-a",
+            "$ cd data-2/x_y",
+            "$ ls",
+            "30 notes 2.txt",
+            "$ ls",
+            "30 notes 2.txt",
+            "$ cd /data-2",
+            "$ rm -rf x_y",
+            "40 stray",
+            "$ ls",
+            "garbage",
+        ];
+        let (state, errors) = parse_transcript(transcript.iter().map(|l| l.to_string()));
+        let kinds: Vec<(usize, LineError)> = errors.into_iter().map(|e| (e.line, e.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (10, LineError::UnknownCommand),
+                (11, LineError::OutputOutsideListing),
+                (13, LineError::Malformed)
+            ]
+        );
+        assert_eq!(state.dir_size(0), 42);
+        assert_eq!(
+            state.lookup("/data-2/x_y").map(|f| state.dir_size(f)),
+            Some(30)
+        );
+    }
+}
@@ -1,6 +1,12 @@
+use std::env;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::rc::Rc;
+use std::str::FromStr;
+
+// Grid traversal utility, not needed by the survey itself.
+#[allow(dead_code)]
+mod spiral;
 
 struct HeightLayout {
     trees: Rc<Vec<Vec<i8>>>,
@@ -14,307 +20,310 @@ impl HeightLayout {
     }
 
     fn width(&self) -> usize {
-        return self.trees[0].len();
+        self.trees[0].len()
     }
 
     fn height(&self) -> usize {
-        return self.trees.len();
+        self.trees.len()
     }
+}
+
+// Visibility from outside the forest and scenic score of every tree.
+struct Survey {
+    visible: Vec<Vec<bool>>,
+    scenic: Vec<Vec<u64>>,
+}
 
-    fn spiral_iter(&self) -> Spiral<i8> {
-        Spiral::<i8>::new(self.trees.clone())
+impl Survey {
+    fn new(layout: &HeightLayout) -> Self {
+        let (width, height) = (layout.width(), layout.height());
+        let mut survey = Self {
+            visible: vec![vec![false; width]; height],
+            scenic: vec![vec![1; width]; height],
+        };
+        for y in 0..height {
+            let line: Vec<[usize; 2]> = (0..width).map(|x| [x, y]).collect();
+            survey.look_along(layout, line.iter());
+            survey.look_along(layout, line.iter().rev());
+        }
+        for x in 0..width {
+            let line: Vec<[usize; 2]> = (0..height).map(|y| [x, y]).collect();
+            survey.look_along(layout, line.iter());
+            survey.look_along(layout, line.iter().rev());
+        }
+        survey
     }
 
-    fn iter_line<'a>(&'a self, i: usize) -> impl DoubleEndedIterator<Item = i8> + 'a {
-        return self.trees[i].iter().map(|v| *v);
+    // Looks at every tree of a line from the side it starts at. The stack
+    // holds the trees not yet hidden by a taller or equal one, so it is
+    // decreasing in height: what a tree pops is what it sees over, and the
+    // stack empties exactly when it is taller than everything before it.
+    fn look_along<'a>(
+        &mut self,
+        layout: &HeightLayout,
+        line: impl Iterator<Item = &'a [usize; 2]>,
+    ) {
+        let mut stack: Vec<(usize, i8)> = Vec::new();
+        for (i, [x, y]) in line.enumerate() {
+            let tree_height = layout.trees[*y][*x];
+            while stack.last().is_some_and(|(_, h)| *h < tree_height) {
+                stack.pop();
+            }
+            let distance = match stack.last() {
+                Some((j, _)) => i - j,
+                None => i,
+            };
+            self.visible[*y][*x] |= stack.is_empty();
+            self.scenic[*y][*x] *= distance as u64;
+            stack.push((i, tree_height));
+        }
     }
 
-    fn iter_column<'a>(&'a self, i: usize) -> impl DoubleEndedIterator<Item = i8> + 'a {
-        return self.trees.iter().map(move |row| row[i].to_owned());
+    fn visible_count(&self) -> usize {
+        self.visible.iter().flatten().filter(|v| **v).count()
     }
-}
 
-#[derive(Clone, Copy)]
-enum Direction {
-    Left,
-    Right,
-    Top,
-    Bottom,
+    fn best_scenic_score(&self) -> u64 {
+        self.scenic.iter().flatten().copied().max().unwrap_or(0)
+    }
 }
 
-struct Spiral<T> {
-    layout: Rc<Vec<Vec<T>>>,
-    position: Option<[usize; 2]>,
-    width: usize,
-    height: usize,
-    direction: Direction,
-    cleared_lines: usize,
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Layer {
+    Heights,
+    Visibility,
+    Scenic,
 }
 
-impl<T> Spiral<T> {
-    fn new(layout: Rc<Vec<Vec<T>>>) -> Self {
-        let width = layout[0].len();
-        let height = layout.len();
-        Self {
-            direction: Direction::Right,
-            layout: layout,
-            position: Some([0, 0]),
-            width,
-            height,
-            cleared_lines: 0,
+impl FromStr for Layer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "heights" => Ok(Layer::Heights),
+            "visibility" => Ok(Layer::Visibility),
+            "scenic" => Ok(Layer::Scenic),
+            _ => Err(format!("Unknown layer {}", s)),
         }
     }
 }
 
-struct CellValue<T> {
-    position: [usize; 2],
-    value: T,
+// One value per tree scaled to 0..=1, and what the ends of the scale mean.
+struct Field {
+    values: Vec<Vec<f64>>,
+    legend: String,
 }
 
-impl<T: Copy> Iterator for Spiral<T> {
-    type Item = CellValue<T>;
+impl Field {
+    fn new(layout: &HeightLayout, survey: &Survey, layer: Layer) -> Self {
+        match layer {
+            Layer::Heights => Self {
+                values: map_grid(&layout.trees, |h| *h as f64 / 9.0),
+                legend: String::from("tree height, linear from 0 to 9"),
+            },
+            Layer::Visibility => Self {
+                values: map_grid(&survey.visible, |v| if *v { 1.0 } else { 0.0 }),
+                legend: String::from("hidden trees at 0, trees visible from outside at 1"),
+            },
+            Layer::Scenic => {
+                // Scores span several orders of magnitude, a log scale keeps
+                // the small ones apart.
+                let best = (survey.best_scenic_score() as f64).ln_1p().max(1.0);
+                Self {
+                    values: map_grid(&survey.scenic, |s| (*s as f64).ln_1p() / best),
+                    legend: format!(
+                        "scenic score, logarithmic from 0 to {}",
+                        survey.best_scenic_score()
+                    ),
+                }
+            }
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.position {
-            None => return None,
-            Some([x, y]) => {
-                let value = self.layout[y][x];
-                let result = Some(CellValue::<T> {
-                    position: [x, y],
-                    value,
-                });
-                self.position = match (self.direction, x, y) {
-                    (Direction::Right, x, y) if x < self.width - 1 - self.cleared_lines => {
-                        Some([x + 1, y])
-                    }
-                    (Direction::Right, x, y) if y < self.height - 1 - self.cleared_lines => {
-                        self.direction = Direction::Bottom;
-                        Some([x, y + 1])
-                    }
-                    (Direction::Right, _, _) => None,
-                    (Direction::Bottom, x, y) if y < self.height - 1 - self.cleared_lines => {
-                        Some([x, y + 1])
-                    }
-                    (Direction::Bottom, x, y) if x > self.cleared_lines => {
-                        self.direction = Direction::Left;
-                        Some([x - 1, y])
-                    }
-                    (Direction::Bottom, _, _) => None,
-                    (Direction::Left, x, y) if x > self.cleared_lines => Some([x - 1, y]),
-                    (Direction::Left, x, y) if y > self.cleared_lines => {
-                        self.direction = Direction::Top;
-                        Some([x, y - 1])
-                    }
-                    (Direction::Left, _, _) => None,
-                    (Direction::Top, x, y) if y > self.cleared_lines + 1 => Some([x, y - 1]),
-                    (Direction::Top, x, y) if x < self.width - 1 - self.cleared_lines => {
-                        self.cleared_lines += 1;
-                        self.direction = Direction::Right;
-                        Some([x + 1, y])
-                    }
-                    (Direction::Top, _, _) => None,
+    // Pixels of the field, `scale` pixels per tree, followed by a strip
+    // showing the colour scale from 0 on the left to 1 on the right.
+    fn render(&self, scale: usize, colour: bool) -> (usize, usize, Vec<u8>) {
+        let width = self.values[0].len() * scale;
+        let strip = (2 * scale).max(4);
+        let height = self.values.len() * scale + scale + strip;
+        let channels = if colour { 3 } else { 1 };
+        let mut data = Vec::with_capacity(width * height * channels);
+        for y in 0..height {
+            for x in 0..width {
+                let value = match y / scale {
+                    row if row < self.values.len() => Some(self.values[row][x / scale]),
+                    row if row == self.values.len() => None,
+                    _ => Some(x as f64 / (width - 1).max(1) as f64),
+                };
+                let pixel = match (value, colour) {
+                    (None, _) => [0, 0, 0],
+                    (Some(t), true) => heat(t),
+                    (Some(t), false) => [grey(t); 3],
                 };
-                result
+                data.extend_from_slice(&pixel[..channels]);
             }
         }
+        (width, height, data)
     }
-}
-
-struct VisibleIterator<'a> {
-    tallest: Option<i8>,
-    iterator: Box<dyn Iterator<Item = i8> + 'a>,
-}
 
-impl<'a, T> From<T> for VisibleIterator<'a>
-where
-    T: Iterator<Item = i8> + 'a,
-{
-    fn from(value: T) -> Self {
-        Self {
-            tallest: None,
-            iterator: Box::new(value),
+    // Writes a grayscale PGM, or a false-colour PPM or PNG, depending on the
+    // extension of the file, which must be one of those.
+    fn export(&self, path: &str, scale: usize) -> std::io::Result<()> {
+        let extension = [".pgm", ".ppm", ".png"]
+            .into_iter()
+            .find(|e| path.ends_with(e))
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("{} is not a .pgm, .ppm or .png file", path),
+                )
+            })?;
+        let colour = extension != ".pgm";
+        let (width, height, data) = self.render(scale, colour);
+        let mut writer = BufWriter::new(File::create(path)?);
+        if extension == ".png" {
+            let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder
+                .add_text_chunk(String::from("Legend"), self.legend.clone())
+                .map_err(std::io::Error::other)?;
+            let mut writer = encoder.write_header().map_err(std::io::Error::other)?;
+            writer
+                .write_image_data(&data)
+                .map_err(std::io::Error::other)?;
+        } else {
+            let magic = if colour { "P6" } else { "P5" };
+            write!(
+                writer,
+                "{}\n# {}\n{} {}\n255\n",
+                magic, self.legend, width, height
+            )?;
+            writer.write_all(&data)?;
         }
+        Ok(())
     }
 }
 
-impl<'a> Iterator for VisibleIterator<'a> {
-    type Item = bool;
+fn map_grid<T, F: Fn(&T) -> f64>(grid: &[Vec<T>], f: F) -> Vec<Vec<f64>> {
+    grid.iter()
+        .map(|row| row.iter().map(&f).collect())
+        .collect()
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match (self.iterator.next(), self.tallest) {
-            (None, _) => None,
-            (Some(value), None) => {
-                self.tallest = Some(value);
-                Some(true)
-            }
-            (Some(value), Some(tallest)) if value > tallest => {
-                self.tallest = Some(value);
-                Some(true)
-            }
-            (_, _) => Some(false),
-        }
-    }
+fn grey(t: f64) -> u8 {
+    (t.clamp(0.0, 1.0) * 255.0).round() as u8
 }
 
-struct VisibleTrees {
-    visible: Vec<Vec<bool>>,
+// False-colour scale going through black, blue, red, yellow and white.
+fn heat(t: f64) -> [u8; 3] {
+    let stops: [[f64; 3]; 5] = [
+        [0.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [1.0, 1.0, 1.0],
+    ];
+    let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+    let i = (position.floor() as usize).min(stops.len() - 2);
+    let f = position - i as f64;
+    [0, 1, 2].map(|c| grey(stops[i][c] + (stops[i + 1][c] - stops[i][c]) * f))
 }
 
-impl VisibleTrees {
-    fn from_iterators(mut iterators: Vec<VisibleIterator>) -> Self {
-        Self {
-            visible: iterators.iter_mut().map(|it| it.collect()).collect(),
-        }
-    }
+fn parse_layout(lines: impl Iterator<Item = String>) -> HeightLayout {
+    HeightLayout::new(
+        lines
+            .filter(|l| !l.is_empty())
+            .map(|content| {
+                content
+                    .chars()
+                    .map(|c| i8::try_from(c.to_digit(10).unwrap()).unwrap())
+                    .collect()
+            })
+            .collect::<Vec<Vec<i8>>>(),
+    )
+}
 
-    fn from_layout<'a>(layout: &'a HeightLayout, direction: Direction) -> Self {
-        match direction {
-            Direction::Bottom => {
-                let iterators: Vec<VisibleIterator> = (0..layout.width())
-                    .map(|i| VisibleIterator::from(layout.iter_column(i)))
-                    .collect();
-                Self::from_iterators(iterators).transpose()
-            }
-            Direction::Left => {
-                let iterators: Vec<VisibleIterator> = (0..layout.height())
-                    .map(|i| VisibleIterator::from(layout.iter_line(i).rev()))
-                    .collect();
-                Self::from_iterators(iterators).reverse_x()
-            }
-            Direction::Top => {
-                let iterators: Vec<VisibleIterator> = (0..layout.width())
-                    .map(|i| VisibleIterator::from(layout.iter_column(i).rev()))
-                    .collect();
-                Self::from_iterators(iterators).reverse_x().transpose()
+fn main() -> std::io::Result<()> {
+    let usage =
+        "Usage: dec8 [--export heights|visibility|scenic <file.pgm|file.ppm|file.png>]... [--scale <pixels>]";
+    let mut exports: Vec<(Layer, String)> = Vec::new();
+    let mut scale = 4;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--export" => {
+                let layer = args.next().expect(usage).parse().expect(usage);
+                exports.push((layer, args.next().expect(usage)));
             }
-            Direction::Right => {
-                let iterators: Vec<VisibleIterator> = (0..layout.height())
-                    .map(|i| VisibleIterator::from(layout.iter_line(i)))
-                    .collect();
-                Self::from_iterators(iterators)
+            "--scale" => {
+                scale = match args.next().expect(usage).parse() {
+                    Ok(n) if n > 0 => n,
+                    _ => panic!("{}", usage),
+                }
             }
+            _ => panic!("{}", usage),
         }
     }
-
-    fn combine(&self, other: &Self) -> Self {
-        let value = self
-            .visible
-            .iter()
-            .zip(other.visible.iter())
-            .map(|(left, right)| {
-                left.into_iter()
-                    .zip(right.into_iter())
-                    .map(|(c1, c2)| *c1 || *c2)
-                    .collect()
-            })
-            .collect();
-        Self { visible: value }
+    let file = File::open("./src/input.txt")?;
+    let lines = BufReader::new(file).lines().map(|l| l.unwrap());
+    let layout = parse_layout(lines);
+    let survey = Survey::new(&layout);
+    println!(
+        "{} trees are visible from outside the grid",
+        survey.visible_count()
+    );
+    println!("Largest scenary score is {}", survey.best_scenic_score());
+    for (layer, path) in exports {
+        let field = Field::new(&layout, &survey, layer);
+        field.export(&path, scale)?;
+        println!("Exported {:?} to {} ({})", layer, path, field.legend);
     }
+    Ok(())
+}
 
-    fn transpose(&self) -> Self {
-        let width = self.visible.len();
-        let height = self.visible[0].len();
-        let mut visible: Vec<Vec<bool>> = Vec::with_capacity(height);
-        for y in 0..height {
-            visible.push((0..width).map(|x| self.visible[x][y]).collect());
-        }
-        Self { visible }
-    }
+#[cfg(test)]
+mod tests {
+    use std::fs;
 
-    fn reverse_x(&self) -> Self {
-        let width = self.visible.len();
-        let height = self.visible[0].len();
-        let mut visible: Vec<Vec<bool>> = Vec::with_capacity(height);
-        for y in 0..height {
-            visible.push((0..width).map(|x| self.visible[y][width - x - 1]).collect());
-        }
-        Self { visible }
+    use crate::spiral::Spiral;
+    use crate::{heat, parse_layout, Field, Layer, Survey};
+
+    #[test]
+    fn test_example() {
+        let content = fs::read_to_string("./src/test.txt").unwrap();
+        let layout = parse_layout(content.lines().map(String::from));
+        let survey = Survey::new(&layout);
+        assert_eq!(survey.visible_count(), 21);
+        assert_eq!(survey.best_scenic_score(), 8);
+        assert_eq!(survey.scenic[1][2], 4);
+        assert_eq!(survey.visible[1], vec![true, true, true, false, true]);
     }
 
-    fn print(&self) {
-        self.visible.iter().for_each(|line| {
-            let line_str = line
-                .iter()
-                .map(|v| match v {
-                    true => "T",
-                    false => "F",
-                })
-                .collect::<Vec<&str>>()
-                .join("");
-            println!("{}", line_str);
-        });
+    #[test]
+    fn test_render() {
+        let content = fs::read_to_string("./src/test.txt").unwrap();
+        let layout = parse_layout(content.lines().map(String::from));
+        let survey = Survey::new(&layout);
+        let field = Field::new(&layout, &survey, Layer::Heights);
+        let (width, height, data) = field.render(2, false);
+        assert_eq!((width, height), (10, 16));
+        assert_eq!(data.len(), 160);
+        // Top left tree has height 3, the legend strip ends in white.
+        assert_eq!(data[0], 85);
+        assert_eq!(data[159], 255);
+        let (_, _, data) = Field::new(&layout, &survey, Layer::Scenic).render(1, true);
+        assert_eq!(data.len(), 5 * 10 * 3);
+        assert_eq!(heat(0.0), [0, 0, 0]);
+        assert_eq!(heat(0.5), [255, 0, 0]);
+        assert_eq!(heat(1.0), [255, 255, 255]);
+        assert!(field.export("heights.jpg", 1).is_err());
     }
-}
 
-fn main() -> std::io::Result<()> {
-    let file = File::open("./src/input.txt")?;
-    let lines = BufReader::new(file).lines();
-    let layout = HeightLayout::new(
-        lines
-            .map(|l| {
-                let content = l.unwrap();
-                let heights: Vec<i8> = content
-                    .chars()
-                    .map(|c| i8::try_from(c.to_digit(10).unwrap()).unwrap())
-                    .collect();
-                return heights;
-            })
-            .collect::<Vec<Vec<i8>>>(),
-    );
-    let height = layout.height();
-    let width = layout.width();
-    let highest_vis = layout
-        .trees
-        .iter()
-        .enumerate()
-        .map(|(y, row)| {
-            row.iter()
-                .enumerate()
-                .map(|(x, tree_height)| {
-                    let mut pos_x = x;
-                    let mut pos_y = y;
-                    let mut top_score = 0;
-                    let mut bottom_score = 0;
-                    let mut left_score = 0;
-                    let mut right_score = 0;
-                    while pos_x < width - 1 {
-                        right_score += 1;
-                        pos_x += 1;
-                        if layout.trees[y][pos_x] >= *tree_height {
-                            break;
-                        }
-                    }
-                    pos_x = x;
-                    while pos_x > 0 {
-                        left_score += 1;
-                        pos_x -= 1;
-                        if layout.trees[y][pos_x] >= *tree_height {
-                            break;
-                        }
-                    }
-                    pos_x = x;
-                    while pos_y < height - 1 {
-                        bottom_score += 1;
-                        pos_y += 1;
-                        if layout.trees[pos_y][x] >= *tree_height {
-                            break;
-                        }
-                    }
-                    pos_y = y;
-                    while pos_y > 0 {
-                        top_score += 1;
-                        pos_y -= 1;
-                        if layout.trees[pos_y][x] >= *tree_height {
-                            break;
-                        }
-                    }
-                    return top_score * bottom_score * left_score * right_score;
-                })
-                .max()
-                .unwrap()
-        })
-        .max()
-        .unwrap();
-    println!("Largest scenary score is {}", highest_vis);
-    Ok(())
+    #[test]
+    fn test_spiral() {
+        let layout = parse_layout(["123", "456", "789"].into_iter().map(String::from));
+        let order: Vec<i8> = Spiral::new(layout.trees.clone()).map(|c| c.value).collect();
+        assert_eq!(order, vec![1, 2, 3, 6, 9, 8, 7, 4, 5]);
+    }
 }
@@ -0,0 +1,87 @@
+use std::rc::Rc;
+
+#[derive(Clone, Copy)]
+enum Direction {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+pub struct Spiral<T> {
+    layout: Rc<Vec<Vec<T>>>,
+    position: Option<[usize; 2]>,
+    width: usize,
+    height: usize,
+    direction: Direction,
+    cleared_lines: usize,
+}
+
+impl<T> Spiral<T> {
+    pub fn new(layout: Rc<Vec<Vec<T>>>) -> Self {
+        let width = layout[0].len();
+        let height = layout.len();
+        Self {
+            direction: Direction::Right,
+            layout,
+            position: Some([0, 0]),
+            width,
+            height,
+            cleared_lines: 0,
+        }
+    }
+}
+
+pub struct CellValue<T> {
+    pub position: [usize; 2],
+    pub value: T,
+}
+
+impl<T: Copy> Iterator for Spiral<T> {
+    type Item = CellValue<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.position {
+            None => None,
+            Some([x, y]) => {
+                let value = self.layout[y][x];
+                let result = Some(CellValue::<T> {
+                    position: [x, y],
+                    value,
+                });
+                self.position = match (self.direction, x, y) {
+                    (Direction::Right, x, y) if x < self.width - 1 - self.cleared_lines => {
+                        Some([x + 1, y])
+                    }
+                    (Direction::Right, x, y) if y < self.height - 1 - self.cleared_lines => {
+                        self.direction = Direction::Bottom;
+                        Some([x, y + 1])
+                    }
+                    (Direction::Right, _, _) => None,
+                    (Direction::Bottom, x, y) if y < self.height - 1 - self.cleared_lines => {
+                        Some([x, y + 1])
+                    }
+                    (Direction::Bottom, x, y) if x > self.cleared_lines => {
+                        self.direction = Direction::Left;
+                        Some([x - 1, y])
+                    }
+                    (Direction::Bottom, _, _) => None,
+                    (Direction::Left, x, y) if x > self.cleared_lines => Some([x - 1, y]),
+                    (Direction::Left, x, y) if y > self.cleared_lines => {
+                        self.direction = Direction::Top;
+                        Some([x, y - 1])
+                    }
+                    (Direction::Left, _, _) => None,
+                    (Direction::Top, x, y) if y > self.cleared_lines + 1 => Some([x, y - 1]),
+                    (Direction::Top, x, y) if x < self.width - 1 - self.cleared_lines => {
+                        self.cleared_lines += 1;
+                        self.direction = Direction::Right;
+                        Some([x + 1, y])
+                    }
+                    (Direction::Top, _, _) => None,
+                };
+                result
+            }
+        }
+    }
+}
@@ -1,17 +1,31 @@
-use std::cell::RefCell;
 use std::collections::HashSet;
+use std::env;
+use std::fmt::Display;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::ops::Add;
-use std::rc::Rc;
+use std::ops::{Add, Sub};
 use std::str::FromStr;
 
-#[derive(Hash, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, Default)]
 struct Position {
     x: i32,
     y: i32,
 }
 
+impl Position {
+    fn signum(self) -> Self {
+        Self {
+            x: self.x.signum(),
+            y: self.y.signum(),
+        }
+    }
+
+    // Chebyshev distance, diagonal neighbours being 1 apart.
+    fn distance(self) -> i32 {
+        self.x.abs().max(self.y.abs())
+    }
+}
+
 impl Add for Position {
     type Output = Position;
 
@@ -23,138 +37,234 @@ impl Add for Position {
     }
 }
 
-enum Movement {
-    Neutral,
-    Offset(Position),
-}
+impl Sub for Position {
+    type Output = Position;
 
-impl Movement {
-    fn new(x: i32, y: i32) -> Self {
-        Self::Offset(Position { x, y })
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::Output {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+        }
     }
 }
 
-enum Next {
-    More(Box<Knot>),
-    Tail(Rc<RefCell<HashSet<Position>>>),
-}
-
-struct Knot {
-    pos: Position,
-    next: Next,
+// Knots from head to tail, with the positions visited by the knots we track.
+// A knot stays put while within `slack` of the one before it, otherwise it
+// steps towards it, diagonally if needed, until it is back within `slack`.
+struct Rope {
+    knots: Vec<Position>,
+    visited: Vec<Option<HashSet<Position>>>,
+    slack: i32,
 }
 
-impl Knot {
-    fn move_by(&mut self, offset: Position) {
-        self.pos = self.pos + offset;
-        self.bring_tail();
+impl Rope {
+    fn new(length: usize, tracked: &[usize], slack: i32) -> Self {
+        assert!(length > 0, "A rope needs at least one knot");
+        let visited = (0..length)
+            .map(|i| {
+                tracked
+                    .contains(&i)
+                    .then(|| HashSet::from([Position::default()]))
+            })
+            .collect();
+        Self {
+            knots: vec![Position::default(); length],
+            visited,
+            slack: slack.max(0),
+        }
     }
 
-    fn bring_tail(&mut self) {
-        match &mut self.next {
-            Next::Tail(recorder) => {
-                recorder.borrow_mut().insert(self.pos);
+    fn move_head(&mut self, offset: Position) {
+        self.knots[0] = self.knots[0] + offset;
+        self.record(0);
+        for i in 1..self.knots.len() {
+            let mut gap = self.knots[i - 1] - self.knots[i];
+            if gap.distance() <= self.slack {
+                // Knots further down did not move either.
+                break;
             }
-            Next::More(next_knot) => {
-                let movement = match (self.pos.x - next_knot.pos.x, self.pos.y - next_knot.pos.y) {
-                    (0, 0) => Movement::Neutral,
-                    (1, 0) => Movement::Neutral,
-                    (0, 1) => Movement::Neutral,
-                    (-1, 0) => Movement::Neutral,
-                    (0, -1) => Movement::Neutral,
-                    (1, 1) => Movement::Neutral,
-                    (-1, -1) => Movement::Neutral,
-                    (-1, 1) => Movement::Neutral,
-                    (1, -1) => Movement::Neutral,
-                    (2, 2) => Movement::new(1, 1),
-                    (-2, -2) => Movement::new(-1, -1),
-                    (2, -2) => Movement::new(1, -1),
-                    (-2, 2) => Movement::new(-1, 1),
-                    (2, y) => Movement::new(1, y),
-                    (-2, y) => Movement::new(-1, y),
-                    (x, 2) => Movement::new(x, 1),
-                    (x, -2) => Movement::new(x, -1),
-                    (x, y) => panic!("Invalid distance found: x {} y {}", x, y),
-                };
-                match movement {
-                    Movement::Neutral => {}
-                    Movement::Offset(off) => next_knot.move_by(off),
-                }
+            while gap.distance() > self.slack {
+                self.knots[i] = self.knots[i] + gap.signum();
+                self.record(i);
+                gap = self.knots[i - 1] - self.knots[i];
             }
         }
     }
-}
 
-struct Rope {
-    head: Knot,
-}
-
-impl Rope {
-    fn new(length: u32, recorder: Rc<RefCell<HashSet<Position>>>) -> Self {
-        let initial_pos = Position { x: 0, y: 0 };
-        recorder.borrow_mut().insert(initial_pos.clone());
-        let tail = Knot {
-            pos: initial_pos.clone(),
-            next: Next::Tail(recorder),
-        };
-        let mut head = tail;
-        for _ in 0..length - 1 {
-            head = Knot {
-                pos: initial_pos.clone(),
-                next: Next::More(Box::new(head)),
-            }
+    fn record(&mut self, knot: usize) {
+        if let Some(visited) = &mut self.visited[knot] {
+            visited.insert(self.knots[knot]);
         }
-        Self { head }
     }
 
-    fn move_up(&mut self) {
-        self.head.move_by(Position { x: 0, y: 1 });
+    fn visited(&self, knot: usize) -> Option<usize> {
+        self.visited.get(knot)?.as_ref().map(|v| v.len())
     }
+}
+
+#[derive(Debug)]
+struct ParseError {
+    content: String,
+}
 
-    fn move_down(&mut self) {
-        self.head.move_by(Position { x: 0, y: -1 });
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid command {:?}", self.content)
     }
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    // Moves the head one cell at a time, possibly diagonally.
+    Step(Position, u32),
+    // Moves the head by any offset at once.
+    Jump(Position),
+}
 
-    fn move_left(&mut self) {
-        self.head.move_by(Position { x: -1, y: 0 });
+// Reads `U 4`, `DL 2`... or `J <dx> <dy>` for a jump.
+fn parse_command(content: &str) -> Result<Command, ParseError> {
+    let error = || ParseError {
+        content: content.to_string(),
+    };
+    let mut parts = content.split_whitespace();
+    let command = parts.next().ok_or_else(error)?;
+    let mut numbers = parts.map(i32::from_str);
+    let result = match (command, numbers.next(), numbers.next()) {
+        ("J", Some(Ok(x)), Some(Ok(y))) => Command::Jump(Position { x, y }),
+        (_, Some(Ok(quantity)), None) if quantity >= 0 && command.len() <= 2 => {
+            let offset = command.chars().try_fold(Position::default(), |acc, c| {
+                let step = match c {
+                    'U' => Position { x: 0, y: 1 },
+                    'D' => Position { x: 0, y: -1 },
+                    'L' => Position { x: -1, y: 0 },
+                    'R' => Position { x: 1, y: 0 },
+                    _ => return None,
+                };
+                // Both letters must be on different axes.
+                ((acc.x == 0 || step.x == 0) && (acc.y == 0 || step.y == 0)).then(|| acc + step)
+            });
+            match offset {
+                Some(offset) if offset != Position::default() => {
+                    Command::Step(offset, quantity as u32)
+                }
+                _ => return Err(error()),
+            }
+        }
+        _ => return Err(error()),
+    };
+    if numbers.next().is_some() {
+        return Err(error());
     }
+    Ok(result)
+}
 
-    fn move_right(&mut self) {
-        self.head.move_by(Position { x: 1, y: 0 });
+fn simulate(commands: &[Command], length: usize, tracked: &[usize], slack: i32) -> Rope {
+    let mut rope = Rope::new(length, tracked, slack);
+    for command in commands {
+        match command {
+            Command::Step(offset, quantity) => {
+                for _ in 0..*quantity {
+                    rope.move_head(*offset);
+                }
+            }
+            Command::Jump(offset) => rope.move_head(*offset),
+        }
     }
+    rope
 }
 
 fn main() -> std::io::Result<()> {
-    let file = File::open("./src/input.txt")?;
-    let lines = BufReader::new(file).lines();
-    let position_recorder = Rc::new(RefCell::new(HashSet::<Position>::new()));
-    let mut rope = Rope::new(10, position_recorder.clone());
-    for line in lines {
-        let content = line.unwrap();
-        let mut chars = content.chars();
-        let command = chars.next().unwrap();
-        let quantity = i32::from_str(&content[2..]).unwrap();
-        for _ in 0..quantity {
-            match command {
-                'D' => {
-                    rope.move_down();
-                }
-                'U' => {
-                    rope.move_up();
-                }
-                'L' => {
-                    rope.move_left();
+    let usage = "Usage: dec9 [--knots <count>] [--track <knot>]... [--slack <cells>]";
+    let mut length = 10;
+    let mut slack = 1;
+    let mut tracked = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--knots" => {
+                length = match args.next().expect(usage).parse() {
+                    Ok(n) if n > 0 => n,
+                    _ => panic!("{}", usage),
                 }
-                'R' => {
-                    rope.move_right();
-                }
-                c => panic!("Unexpected character {}", c),
             }
+            "--slack" => slack = args.next().expect(usage).parse().expect(usage),
+            "--track" => tracked.push(args.next().expect(usage).parse().expect(usage)),
+            _ => panic!("{}", usage),
+        }
+    }
+    if tracked.is_empty() {
+        tracked = vec![1, 9];
+    }
+    let file = File::open("./src/input.txt")?;
+    let commands: Vec<Command> = BufReader::new(file)
+        .lines()
+        .map(|l| parse_command(&l.unwrap()).unwrap_or_else(|e| panic!("{}", e)))
+        .collect();
+    let rope = simulate(&commands, length, &tracked, slack);
+    for knot in tracked {
+        match rope.visited(knot) {
+            Some(count) => println!("Knot {} has gone through {} positions", knot, count),
+            None => println!("The rope has no knot {}", knot),
         }
     }
-    println!(
-        "The tail has gone through {} positions",
-        position_recorder.borrow().len()
-    );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{parse_command, simulate, Command, Position};
+
+    fn commands(content: &str) -> Vec<Command> {
+        content.lines().map(|l| parse_command(l).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_examples() {
+        let small = commands("R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2");
+        let rope = simulate(&small, 10, &[1, 9], 1);
+        assert_eq!(rope.visited(1), Some(13));
+        assert_eq!(rope.visited(9), Some(1));
+        let larger = commands(&fs::read_to_string("./src/test.txt").unwrap());
+        let rope = simulate(&larger, 10, &[9], 1);
+        assert_eq!(rope.visited(9), Some(36));
+        assert_eq!(rope.visited(1), None);
+        assert!(parse_command("X 3").is_err());
+    }
+
+    #[test]
+    fn test_commands() {
+        assert_eq!(
+            parse_command("UL 3").unwrap(),
+            Command::Step(Position { x: -1, y: 1 }, 3)
+        );
+        assert_eq!(
+            parse_command("J -4 12").unwrap(),
+            Command::Jump(Position { x: -4, y: 12 })
+        );
+        for invalid in ["UD 1", "RR 2", "URL 1", "U", "U 1 2", "J 3", "U -1"] {
+            assert!(parse_command(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_large_gaps() {
+        let rope = simulate(&commands("UR 3"), 2, &[1], 1);
+        assert_eq!(rope.visited(1), Some(3));
+        let rope = simulate(&commands("J 5 2"), 3, &[1, 2], 1);
+        assert_eq!(
+            rope.knots,
+            vec![
+                Position { x: 5, y: 2 },
+                Position { x: 4, y: 2 },
+                Position { x: 3, y: 2 }
+            ]
+        );
+        assert_eq!(rope.visited(1), Some(5));
+        assert_eq!(rope.visited(2), Some(4));
+        let rope = simulate(&commands("R 4\nJ 0 -6"), 2, &[1], 3);
+        assert_eq!(rope.knots[1], Position { x: 4, y: -3 });
+        assert_eq!(rope.visited(1), Some(5));
+    }
+}